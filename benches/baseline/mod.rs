@@ -0,0 +1,173 @@
+//! `build_boundary` as it was before the ways were chained through an
+//! endpoint index, without its logging, to compare with: the ways are
+//! scanned for one touching the end of the ring being built, and removed
+//! from their vector, which is quadratic in the number of ways.
+
+use geo_types::{Coord, LineString, MultiPolygon, Polygon};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+
+/// Wrapper arround a Vec<osmpbfreader::Node> that has length at least 2.
+struct BoundaryPart {
+    nodes: Vec<osmpbfreader::Node>,
+}
+
+impl BoundaryPart {
+    fn new(nodes: Vec<osmpbfreader::Node>) -> Option<Self> {
+        if nodes.len() >= 2 {
+            Some(Self { nodes })
+        } else {
+            None
+        }
+    }
+
+    fn first(&self) -> osmpbfreader::NodeId {
+        self.nodes.first().unwrap().id
+    }
+
+    fn last(&self) -> osmpbfreader::NodeId {
+        self.nodes.last().unwrap().id
+    }
+
+    fn reverse(&mut self) {
+        self.nodes.reverse();
+    }
+
+    fn into_vec(self) -> Vec<osmpbfreader::Node> {
+        self.nodes
+    }
+}
+
+fn get_nodes<T: Borrow<osmpbfreader::OsmObj>>(
+    way: &osmpbfreader::Way,
+    objects: &BTreeMap<osmpbfreader::OsmId, T>,
+) -> Vec<osmpbfreader::Node> {
+    way.nodes
+        .iter()
+        .filter_map(|node_id| objects.get(&osmpbfreader::OsmId::Node(*node_id)))
+        .filter_map(|node_obj| {
+            if let osmpbfreader::OsmObj::Node(ref node) = *node_obj.borrow() {
+                Some(node.clone())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+pub fn build_boundary<T: Borrow<osmpbfreader::OsmObj>>(
+    relation: &osmpbfreader::Relation,
+    objects: &BTreeMap<osmpbfreader::OsmId, T>,
+) -> Option<MultiPolygon<f64>> {
+    use geo::prelude::Intersects;
+
+    let mut outer_polys = build_boundary_parts(relation, objects, vec!["outer", "enclave", ""]);
+    let inner_polys = build_boundary_parts(relation, objects, vec!["inner"]);
+
+    if let Some(ref mut outers) = outer_polys {
+        if let Some(inners) = inner_polys {
+            inners.into_iter().for_each(|inner| {
+                let (exterior, _) = inner.into_inner();
+                for outer in outers.0.iter_mut() {
+                    if exterior.lines().all(|line| outer.intersects(&line)) {
+                        outer.interiors_push(exterior);
+                        break;
+                    }
+                }
+            })
+        }
+    }
+    outer_polys
+}
+
+fn build_boundary_parts<T: Borrow<osmpbfreader::OsmObj>>(
+    relation: &osmpbfreader::Relation,
+    objects: &BTreeMap<osmpbfreader::OsmId, T>,
+    roles: Vec<&str>,
+) -> Option<MultiPolygon<f64>> {
+    let mut boundary_parts: Vec<BoundaryPart> = relation
+        .refs
+        .iter()
+        .filter(|r| roles.contains(&r.role.as_str()))
+        .filter_map(|r| objects.get(&r.member))
+        .filter_map(|way_obj| way_obj.borrow().way())
+        .map(|way| get_nodes(way, objects))
+        .filter_map(BoundaryPart::new)
+        .collect();
+    let mut multipoly = MultiPolygon(vec![]);
+
+    let mut append_ring = |nodes: &[osmpbfreader::Node]| {
+        let poly_geom = nodes
+            .iter()
+            .map(|n| Coord {
+                x: n.lon(),
+                y: n.lat(),
+            })
+            .collect();
+        multipoly
+            .0
+            .push(Polygon::new(LineString(poly_geom), vec![]));
+    };
+
+    while !boundary_parts.is_empty() {
+        let first_part = boundary_parts.remove(0);
+        let mut added_nodes: Vec<osmpbfreader::Node> = vec![];
+        let mut node_to_idx: BTreeMap<osmpbfreader::NodeId, usize> = BTreeMap::new();
+
+        let mut add_part = |part: BoundaryPart| {
+            let mut part = part.into_vec();
+
+            let nodes = if added_nodes.is_empty() {
+                part.drain(..)
+            } else {
+                part.drain(1..)
+            };
+
+            for n in nodes {
+                if let Some(start_idx) = node_to_idx.get(&n.id) {
+                    let ring = added_nodes.split_off(*start_idx);
+                    node_to_idx = added_nodes
+                        .iter()
+                        .enumerate()
+                        .map(|(i, n)| (n.id, i))
+                        .collect();
+                    if ring.len() >= 3 {
+                        append_ring(&ring);
+                    }
+                }
+                node_to_idx.insert(n.id, added_nodes.len());
+                added_nodes.push(n);
+            }
+        };
+
+        let mut current = first_part.last();
+        add_part(first_part);
+
+        loop {
+            let mut added_part = false;
+            let mut i = 0;
+            while i < boundary_parts.len() {
+                if current == boundary_parts[i].first() {
+                    current = boundary_parts[i].last();
+                    add_part(boundary_parts.remove(i));
+                    added_part = true;
+                } else if current == boundary_parts[i].last() {
+                    current = boundary_parts[i].first();
+                    boundary_parts[i].reverse();
+                    add_part(boundary_parts.remove(i));
+                    added_part = true;
+                } else {
+                    i += 1;
+                }
+            }
+            if !added_part {
+                break;
+            }
+        }
+    }
+    if multipoly.0.is_empty() {
+        None
+    } else {
+        Some(multipoly)
+    }
+}
@@ -0,0 +1,118 @@
+#[macro_use]
+extern crate criterion;
+extern crate geo;
+extern crate geo_types;
+extern crate osm_boundaries_utils;
+extern crate osmpbfreader;
+
+use criterion::{BenchmarkId, Criterion};
+use geo_types::Point;
+use osm_boundaries_utils::osm_builder::OsmBuilder;
+use osm_boundaries_utils::{build_boundary, NodeLocationStore, OsmObjectStore};
+
+mod baseline;
+
+/// A relation made of a circle split into `nb_ways` ways of 10 segments,
+/// listed in a scrambled order, every other one being reversed.
+fn circle_relation(nb_ways: usize) -> (OsmBuilder, osmpbfreader::Relation) {
+    const SEGMENTS_PER_WAY: usize = 10;
+    let nb_points = nb_ways * SEGMENTS_PER_WAY;
+    let point = |i: usize| {
+        let angle = 2. * std::f64::consts::PI * (i % nb_points) as f64 / nb_points as f64;
+        Point::new(angle.cos() * 10., angle.sin() * 10.)
+    };
+
+    let mut builder = OsmBuilder::new();
+    let mut ways = vec![];
+    for w in 0..nb_ways {
+        let start = w * SEGMENTS_PER_WAY;
+        let mut coords: Vec<_> = (start..=start + SEGMENTS_PER_WAY)
+            .map(|i| {
+                // the ends of the ways are named to be shared with the next way
+                let name = if i % SEGMENTS_PER_WAY == 0 {
+                    Some(format!("junction {}", i % nb_points))
+                } else {
+                    None
+                };
+                (point(i), name)
+            })
+            .collect();
+        if w % 2 == 1 {
+            coords.reverse();
+        }
+        ways.push(builder.way(coords));
+    }
+
+    let relation_id = builder.relation().relation_id;
+    let mut relation = builder.objects[&relation_id.into()]
+        .relation()
+        .unwrap()
+        .clone();
+    // 7919 is prime, so this visits every way once in a scrambled order as
+    // long as it does not divide the number of ways
+    assert_ne!(nb_ways % 7919, 0);
+    relation.refs = (0..nb_ways)
+        .map(|i| osmpbfreader::Ref {
+            role: "outer".into(),
+            member: ways[(i * 7919) % nb_ways].into(),
+        })
+        .collect();
+    (builder, relation)
+}
+
+/// Node ids of the member ways of a relation.
+fn member_ways(
+    builder: &OsmBuilder,
+    relation: &osmpbfreader::Relation,
+) -> Vec<Vec<osmpbfreader::NodeId>> {
+    relation
+        .refs
+        .iter()
+        .filter_map(|r| builder.objects.way(r.member.way()?))
+        .map(|way| way.nodes.clone())
+        .collect()
+}
+
+/// `build_boundary` before and after the ways were chained through an
+/// endpoint index, on the same relations and objects.
+fn chaining(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chaining");
+    group.sample_size(10);
+    for nb_ways in [100, 1_000, 5_000, 20_000].iter() {
+        let (builder, relation) = circle_relation(*nb_ways);
+        assert_eq!(
+            baseline::build_boundary(&relation, &builder.objects),
+            build_boundary(&relation, &builder.objects)
+        );
+        group.bench_with_input(BenchmarkId::new("scan", nb_ways), nb_ways, |b, _| {
+            b.iter(|| baseline::build_boundary(&relation, &builder.objects))
+        });
+        group.bench_with_input(BenchmarkId::new("indexed", nb_ways), nb_ways, |b, _| {
+            b.iter(|| build_boundary(&relation, &builder.objects))
+        });
+    }
+    group.finish();
+}
+
+/// Lookups of the ways and nodes alone, whose cost per node grows with the
+/// number of objects as they stop fitting in the CPU caches.
+fn lookups(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lookups");
+    group.sample_size(10);
+    for nb_ways in [100, 1_000, 5_000, 20_000].iter() {
+        let (builder, relation) = circle_relation(*nb_ways);
+        group.bench_with_input(BenchmarkId::from_parameter(nb_ways), nb_ways, |b, _| {
+            b.iter(|| {
+                member_ways(&builder, &relation)
+                    .iter()
+                    .flatten()
+                    .filter_map(|&id| builder.objects.node_location(id))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, chaining, lookups);
+criterion_main!(benches);
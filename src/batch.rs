@@ -0,0 +1,87 @@
+extern crate osmpbfreader;
+
+use crate::boundaries::{BoundaryBuilder, BoundaryReport};
+use crate::store::OsmObjectStore;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+#[cfg(test)]
+use crate::osm_builder;
+#[cfg(test)]
+use crate::osm_builder::named_node;
+
+impl BoundaryBuilder {
+    /// Builds the boundaries of all the relations, keyed by relation id.
+    pub fn build_all<S: OsmObjectStore + ?Sized>(
+        &self,
+        relations: &[osmpbfreader::Relation],
+        objects: &S,
+    ) -> BTreeMap<osmpbfreader::RelationId, BoundaryReport> {
+        relations
+            .iter()
+            .map(|relation| (relation.id, self.build(relation, objects)))
+            .collect()
+    }
+
+    /// Same as `build_all`, but builds the boundaries in parallel on the
+    /// rayon thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_build_all<S: OsmObjectStore + Sync + ?Sized>(
+        &self,
+        relations: &[osmpbfreader::Relation],
+        objects: &S,
+    ) -> BTreeMap<osmpbfreader::RelationId, BoundaryReport> {
+        relations
+            .par_iter()
+            .map(|relation| (relation.id, self.build(relation, objects)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+fn batch_relations() -> (osm_builder::OsmBuilder, Vec<osmpbfreader::Relation>) {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let square = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "1"),
+            named_node(4.0, 4.0, "2"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .relation_id;
+    let unclosed = builder
+        .relation()
+        .outer(vec![
+            named_node(5.0, 5.0, "other_start"),
+            named_node(6.0, 5.0, "11"),
+        ])
+        .relation_id;
+    let relations = [square, unclosed]
+        .iter()
+        .map(|id| builder.objects[&(*id).into()].relation().unwrap().clone())
+        .collect();
+    (builder, relations)
+}
+
+#[test]
+fn test_build_all() {
+    let (builder, relations) = batch_relations();
+    let reports = BoundaryBuilder::new().build_all(&relations, &builder.objects);
+    assert_eq!(reports.len(), 2);
+    assert!(reports[&relations[0].id].boundary.is_ok());
+    assert!(reports[&relations[1].id].boundary.is_err());
+    assert!(!reports[&relations[1].id].issues.is_empty());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_build_all() {
+    let (builder, relations) = batch_relations();
+    let reports = BoundaryBuilder::new().par_build_all(&relations, &builder.objects);
+    assert_eq!(reports.len(), 2);
+    assert!(reports[&relations[0].id].boundary.is_ok());
+    assert!(reports[&relations[1].id].boundary.is_err());
+}
@@ -1,9 +1,16 @@
 extern crate osmpbfreader;
 
-use geo_types::{Coord, LineString, MultiPolygon, Point, Polygon};
+use geo_types::{Coord, LineString, MultiPolygon, Point, Polygon, Rect};
 use log::{debug, warn};
-use std::borrow::Borrow;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+
+use crate::store::{NodeLocationStore, OsmObjectStore};
+pub use crate::validation::ValidityIssue;
+use crate::validation::{
+    encloses, is_self_intersecting, is_within, make_valid, validate_multipolygon,
+};
 
 #[cfg(test)]
 use crate::osm_builder;
@@ -12,22 +19,190 @@ use crate::osm_builder::named_node;
 
 const WARN_UNCLOSED_RING_MAX_DISTANCE: f64 = 10.;
 
+/// Reason why no boundary could be built for a relation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoundaryError {
+    /// The relation has no member with an outer role.
+    NoMember,
+    /// None of the outer ways could be found in the objects.
+    MissingMembers,
+    /// The outer members are not ways, e.g. sub-relations that are not
+    /// expanded.
+    NoWayMember,
+    /// The outer ways were found, but not enough of their nodes to make a
+    /// segment.
+    MissingNodes,
+    /// Outer ways were found but could not be chained into a closed ring.
+    UnclosedRing,
+    /// Every closed ring had too few nodes.
+    DegenerateRings,
+}
+
+impl fmt::Display for BoundaryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BoundaryError::NoMember => write!(f, "no outer member"),
+            BoundaryError::MissingMembers => write!(f, "all outer ways are missing"),
+            BoundaryError::NoWayMember => write!(f, "no outer way member"),
+            BoundaryError::MissingNodes => write!(f, "all nodes of the outer ways are missing"),
+            BoundaryError::UnclosedRing => write!(f, "outer ways do not form a closed ring"),
+            BoundaryError::DegenerateRings => write!(f, "all rings have too few nodes"),
+        }
+    }
+}
+
+impl Error for BoundaryError {}
+
+/// Non fatal problem met while building a boundary.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoundaryIssue {
+    /// A way member of the relation is not in the objects.
+    MissingWay(osmpbfreader::WayId),
+    /// A sub-relation member to expand is not in the objects.
+    MissingRelation(osmpbfreader::RelationId),
+    /// A sub-relation member is not expanded as it is one of its own ancestors.
+    RelationCycle(osmpbfreader::RelationId),
+    /// A sub-relation member is not expanded as it is nested too deep.
+    TooDeepRelation(osmpbfreader::RelationId),
+    /// A node of a member way is not in the objects.
+    MissingNode {
+        way: osmpbfreader::WayId,
+        node: osmpbfreader::NodeId,
+    },
+    /// A ring with too few nodes, closed at `node`, has been dropped.
+    DegenerateRing { node: osmpbfreader::NodeId },
+    /// An inner ring, starting at `node`, is not inside any outer ring and has been dropped.
+    UnassignedInner { node: osmpbfreader::NodeId },
+    /// A chain of ways could not be closed, its ends being `distance` meters apart.
+    UnclosedRing {
+        first: osmpbfreader::NodeId,
+        last: osmpbfreader::NodeId,
+        distance: f64,
+    },
+    /// A gap of `distance` meters between two unclosed chain ends has been
+    /// bridged by a straight segment.
+    RepairedGap {
+        from: osmpbfreader::NodeId,
+        to: osmpbfreader::NodeId,
+        distance: f64,
+    },
+    /// The ring starting at `node` crosses or touches itself.
+    SelfIntersection { node: osmpbfreader::NodeId },
+    /// Two ways cross without sharing a node there, and have been split at
+    /// the crossing (only reported by the `RingAssembly::Faces` strategy).
+    CrossingWays {
+        way: osmpbfreader::WayId,
+        other: osmpbfreader::WayId,
+    },
+    /// The ring starting at `node` crosses the antimeridian, see
+    /// `BoundaryBuilder::antimeridian`.
+    AntimeridianCrossing { node: osmpbfreader::NodeId },
+    /// The boundary breaks an OGC validity rule other than self-intersection.
+    InvalidGeometry(ValidityIssue),
+    /// The role of a way disagrees with the role of its ring computed from
+    /// the rings nesting (only reported when roles are ignored).
+    WrongRole {
+        way: osmpbfreader::WayId,
+        role: String,
+        computed: RingRole,
+    },
+}
+
+/// Role of a ring in a boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingRole {
+    Outer,
+    Inner,
+}
+
+/// Result of `BoundaryBuilder::build`: the boundary, or the reason why it
+/// could not be built, along with all the non fatal issues met on the way.
+#[derive(Debug, Clone)]
+pub struct BoundaryReport<B = MultiPolygon<f64>> {
+    pub boundary: Result<B, BoundaryError>,
+    pub issues: Vec<BoundaryIssue>,
+}
+
+/// A closed ring of a boundary, traced back to the OSM objects it is made of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracedRing {
+    /// Id of each node, the first one being repeated at the end as in a
+    /// closed way.
+    pub nodes: Vec<osmpbfreader::NodeId>,
+    /// Location of each node.
+    pub coords: LineString<f64>,
+    /// Way of each segment, `ways[i]` going from `nodes[i]` to `nodes[i + 1]`,
+    /// or `None` for the segments added to repair a gap.
+    pub ways: Vec<Option<osmpbfreader::WayId>>,
+}
+
+impl TracedRing {
+    fn reverse(&mut self) {
+        self.nodes.reverse();
+        self.coords.0.reverse();
+        self.ways.reverse();
+    }
+}
+
+/// A polygon of a boundary, traced back to the OSM objects it is made of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracedPolygon {
+    pub exterior: TracedRing,
+    pub interiors: Vec<TracedRing>,
+}
+
+impl TracedPolygon {
+    pub fn to_polygon(&self) -> Polygon<f64> {
+        Polygon::new(
+            self.exterior.coords.clone(),
+            self.interiors.iter().map(|r| r.coords.clone()).collect(),
+        )
+    }
+}
+
+/// A boundary whose every vertex and segment can be traced back to the OSM
+/// node and way it comes from, see `BoundaryBuilder::build_rings`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BoundaryRings {
+    pub polygons: Vec<TracedPolygon>,
+}
+
+impl BoundaryRings {
+    pub fn to_multipolygon(&self) -> MultiPolygon<f64> {
+        self.polygons.iter().map(|p| p.to_polygon()).collect()
+    }
+}
+
 // Define BoundaryPart in a mod to make its fields private
 mod boundary_part {
-    /// Wrapper arround a Vec<osmpbfreader::Node> that has length at least 2.
+    use geo_types::Coord;
+
+    /// A node of a way, with its location.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Vertex {
+        pub id: osmpbfreader::NodeId,
+        pub coord: Coord<f64>,
+    }
+
+    /// Wrapper arround the Vec<Vertex> of a way that has length at least 2.
     pub struct BoundaryPart {
-        nodes: Vec<osmpbfreader::Node>,
+        way: osmpbfreader::WayId,
+        nodes: Vec<Vertex>,
     }
 
     impl BoundaryPart {
-        pub fn new(nodes: Vec<osmpbfreader::Node>) -> Option<Self> {
+        pub fn new(way: osmpbfreader::WayId, nodes: Vec<Vertex>) -> Option<Self> {
             if nodes.len() >= 2 {
-                Some(Self { nodes })
+                Some(Self { way, nodes })
             } else {
                 None
             }
         }
 
+        pub fn way(&self) -> osmpbfreader::WayId {
+            self.way
+        }
+
         pub fn first(&self) -> osmpbfreader::NodeId {
             self.nodes.first().unwrap().id
         }
@@ -36,31 +211,119 @@ mod boundary_part {
             self.nodes.last().unwrap().id
         }
 
+        pub fn nodes(&self) -> &[Vertex] {
+            &self.nodes
+        }
+
+        pub fn last_segment(&self) -> (Vertex, Vertex) {
+            (
+                self.nodes[self.nodes.len() - 2],
+                self.nodes[self.nodes.len() - 1],
+            )
+        }
+
         pub fn reverse(&mut self) {
             self.nodes.reverse();
         }
 
-        pub fn into_vec(self) -> Vec<osmpbfreader::Node> {
+        pub fn into_vec(self) -> Vec<Vertex> {
             self.nodes
         }
     }
 }
 
-use self::boundary_part::BoundaryPart;
+use self::boundary_part::{BoundaryPart, Vertex};
+
+/// A closed ring, without its closing node repeated.
+struct Ring {
+    nodes: Vec<Vertex>,
+    /// Way of each segment, `ways[i]` being the way from `nodes[i]` to the
+    /// next node, or `None` for the segments added to repair a gap.
+    ways: Vec<Option<osmpbfreader::WayId>>,
+}
+
+impl Ring {
+    fn to_line_string(&self) -> LineString<f64> {
+        self.nodes.iter().map(|n| n.coord).collect()
+    }
+
+    fn into_traced(self) -> TracedRing {
+        let first = self.nodes[0];
+        TracedRing {
+            nodes: self
+                .nodes
+                .iter()
+                .chain(Some(&first))
+                .map(|n| n.id)
+                .collect(),
+            coords: self
+                .nodes
+                .iter()
+                .chain(Some(&first))
+                .map(|n| n.coord)
+                .collect(),
+            ways: self.ways,
+        }
+    }
+}
+
+/// An outer ring with the inner rings inside it.
+struct RingGroup {
+    outer: Ring,
+    inners: Vec<Ring>,
+}
+
+impl RingGroup {
+    fn to_polygon(&self) -> Polygon<f64> {
+        Polygon::new(
+            self.outer.to_line_string(),
+            self.inners.iter().map(|r| r.to_line_string()).collect(),
+        )
+    }
+
+    fn into_traced(self) -> TracedPolygon {
+        TracedPolygon {
+            exterior: self.outer.into_traced(),
+            interiors: self.inners.into_iter().map(|r| r.into_traced()).collect(),
+        }
+    }
+}
+
+/// A chain of ways that could not be closed into a ring.
+struct Chain {
+    nodes: Vec<Vertex>,
+    /// Way of each segment, as in `Ring`, so one less than the nodes.
+    ways: Vec<Option<osmpbfreader::WayId>>,
+}
+
+impl Chain {
+    fn reverse(&mut self) {
+        self.nodes.reverse();
+        self.ways.reverse();
+    }
+}
+
+fn distance(a: &Vertex, b: &Vertex) -> f64 {
+    use geo::haversine_distance::HaversineDistance;
+    Point(a.coord).haversine_distance(&Point(b.coord))
+}
 
-fn get_nodes<T: Borrow<osmpbfreader::OsmObj>>(
+fn get_nodes<S: NodeLocationStore + ?Sized>(
     way: &osmpbfreader::Way,
-    objects: &BTreeMap<osmpbfreader::OsmId, T>,
-) -> Vec<osmpbfreader::Node> {
+    objects: &S,
+    issues: &mut Vec<BoundaryIssue>,
+) -> Vec<Vertex> {
     way.nodes
         .iter()
-        .filter_map(|node_id| objects.get(&osmpbfreader::OsmId::Node(*node_id)))
-        .filter_map(|node_obj| {
-            if let osmpbfreader::OsmObj::Node(ref node) = *node_obj.borrow() {
-                Some(node.clone())
-            } else {
-                None
+        .filter_map(|&id| {
+            let coord = objects.node_location(id);
+            if coord.is_none() {
+                issues.push(BoundaryIssue::MissingNode {
+                    way: way.id,
+                    node: id,
+                });
             }
+            coord.map(|coord| Vertex { id, coord })
         })
         .collect()
 }
@@ -113,7 +376,7 @@ fn test_get_nodes() {
     };
     objects.insert(node_68.id.into(), node_68.into());
 
-    let nodes = get_nodes(&way, &objects);
+    let nodes = get_nodes(&way, &objects, &mut vec![]);
     assert_eq!(nodes.len(), 4);
     assert_eq!(nodes[0].id.0, 12);
     assert_eq!(nodes[1].id.0, 15);
@@ -121,87 +384,788 @@ fn test_get_nodes() {
     assert_eq!(nodes[3].id.0, 68);
 }
 
-pub fn build_boundary<T: Borrow<osmpbfreader::OsmObj>>(
+pub fn build_boundary<S: OsmObjectStore + ?Sized>(
     relation: &osmpbfreader::Relation,
-    objects: &BTreeMap<osmpbfreader::OsmId, T>,
+    objects: &S,
 ) -> Option<MultiPolygon<f64>> {
-    use geo::prelude::Intersects;
-
-    let mut outer_polys = build_boundary_parts(relation, objects, vec!["outer", "enclave", ""]);
-    let inner_polys = build_boundary_parts(relation, objects, vec!["inner"]);
-
-    if let Some(ref mut outers) = outer_polys {
-        if let Some(inners) = inner_polys {
-            inners.into_iter().for_each(|inner| {
-                /*
-                    It's assumed here that the 'inner' ring is contained into
-                    exactly ONE outer ring. To find it among all 'outers', all
-                    we need is to find a candidate 'outer' area that shares a point
-                    point with (i.e 'intersects') all 'inner' segments.
-                    Using 'contains' is not suitable here, as 'inner' may touch its outer
-                    ring at a single point.
-
-                    NB: this algorithm cannot handle "donut inside donut" boundaries
-                    (where 'inner' would be contained into multiple concentric outer rings).
-                */
-                let (exterior, _) = inner.into_inner();
-                for ref mut outer in outers.0.iter_mut() {
-                    if exterior.lines().all(|line| outer.intersects(&line)) {
-                        outer.interiors_push(exterior);
-                        break;
+    build_boundary_checked(relation, objects).boundary.ok()
+}
+
+/// Same as `build_boundary`, but tells why the boundary could not be built
+/// and reports every non fatal issue met while building it.
+pub fn build_boundary_checked<S: OsmObjectStore + ?Sized>(
+    relation: &osmpbfreader::Relation,
+    objects: &S,
+) -> BoundaryReport {
+    BoundaryBuilder::new().build(relation, objects)
+}
+
+/// How inner rings are matched with the outer ring they are a hole of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InnerMatching {
+    /// The smallest outer ring enclosing the inner, which handles nested
+    /// outers (e.g. an island in a lake).
+    SmallestEnclosing,
+    /// The first outer ring enclosing the inner, the outer rings being in
+    /// the order of their first member.
+    FirstEnclosing,
+}
+
+/// How the ways of the members are assembled into rings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingAssembly {
+    /// Ways are chained end to end, which is fast but sensitive to
+    /// duplicated members and overlapping ways.
+    Chaining,
+    /// The segments of the ways make a planar graph, split at the nodes
+    /// shared by the ways and at their crossings, whose faces are the rings.
+    /// Duplicated segments are merged, the outer faces (by their nesting
+    /// depth) sharing a segment are merged, and dangling segments are
+    /// reported as unclosed rings. The vertices added at the crossings have
+    /// negative node ids.
+    Faces,
+}
+
+/// Winding order to which the rings are normalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingWinding {
+    /// Counter-clockwise exteriors and clockwise interiors, as required by
+    /// RFC 7946 (GeoJSON) and the OGC simple features.
+    CounterClockwise,
+    /// Clockwise exteriors and counter-clockwise interiors.
+    Clockwise,
+}
+
+/// How the boundaries crossing the antimeridian are built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Antimeridian {
+    /// The longitudes are kept as they are, so the rings crossing the
+    /// antimeridian span the whole globe.
+    Keep,
+    /// The longitudes are shifted to be continuous across the antimeridian,
+    /// the ones of the western hemisphere being above 180.
+    Shift,
+    /// The shifted boundary is split along the antimeridian, the parts of
+    /// the western hemisphere being shifted back.
+    Split,
+}
+
+/// Settings of the boundary building, see `BoundaryBuilder`.
+#[derive(Debug, Clone)]
+pub struct BoundaryOptions {
+    /// Roles of the members making the outer rings.
+    pub outer_roles: Vec<String>,
+    /// Roles of the members making the inner rings.
+    pub inner_roles: Vec<String>,
+    /// Tells outer rings from inner rings by their nesting rather than by roles.
+    pub ignore_roles: bool,
+    pub inner_matching: InnerMatching,
+    pub ring_assembly: RingAssembly,
+    /// Rings with less nodes are dropped.
+    pub min_ring_nodes: usize,
+    /// Unclosed rings whose ends are closer than this many meters are logged as warnings.
+    pub warn_unclosed_distance: f64,
+    /// Levels of sub-relation members whose members are used as the
+    /// relation's own, 0 meaning that sub-relations are ignored.
+    pub max_subrelation_depth: usize,
+    /// Gaps shorter than this many meters are bridged to close rings.
+    pub repair_tolerance: Option<f64>,
+    /// Checks the rings for self-intersections, and the boundary for the
+    /// other OGC validity rules.
+    pub validate: bool,
+    /// Rebuilds the invalid boundaries into valid ones.
+    pub make_valid: bool,
+    /// Winding of the exterior rings, the interiors having the opposite one.
+    /// The rings keep the winding of their ways if `None`.
+    pub winding: Option<RingWinding>,
+    pub antimeridian: Antimeridian,
+}
+
+impl Default for BoundaryOptions {
+    fn default() -> Self {
+        BoundaryOptions {
+            outer_roles: vec!["outer".into(), "enclave".into(), "".into()],
+            inner_roles: vec!["inner".into()],
+            ignore_roles: false,
+            inner_matching: InnerMatching::SmallestEnclosing,
+            ring_assembly: RingAssembly::Chaining,
+            min_ring_nodes: 3,
+            warn_unclosed_distance: WARN_UNCLOSED_RING_MAX_DISTANCE,
+            max_subrelation_depth: 0,
+            repair_tolerance: None,
+            validate: false,
+            make_valid: false,
+            winding: None,
+            antimeridian: Antimeridian::Keep,
+        }
+    }
+}
+
+/// Builds boundaries with non default settings.
+#[derive(Debug, Clone, Default)]
+pub struct BoundaryBuilder {
+    options: BoundaryOptions,
+}
+
+impl BoundaryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: BoundaryOptions) -> Self {
+        BoundaryBuilder { options }
+    }
+
+    pub fn options(&self) -> &BoundaryOptions {
+        &self.options
+    }
+
+    pub fn outer_roles(mut self, roles: &[&str]) -> Self {
+        self.options.outer_roles = roles.iter().map(|r| r.to_string()).collect();
+        self
+    }
+
+    pub fn inner_roles(mut self, roles: &[&str]) -> Self {
+        self.options.inner_roles = roles.iter().map(|r| r.to_string()).collect();
+        self
+    }
+
+    /// Ignores the roles of the members, and tells outer rings from inner
+    /// rings by their nesting depth: rings enclosed by an even number of
+    /// rings are outers, the others are inners.
+    pub fn ignore_roles(mut self, ignore_roles: bool) -> Self {
+        self.options.ignore_roles = ignore_roles;
+        self
+    }
+
+    pub fn inner_matching(mut self, inner_matching: InnerMatching) -> Self {
+        self.options.inner_matching = inner_matching;
+        self
+    }
+
+    pub fn ring_assembly(mut self, ring_assembly: RingAssembly) -> Self {
+        self.options.ring_assembly = ring_assembly;
+        self
+    }
+
+    pub fn min_ring_nodes(mut self, min_ring_nodes: usize) -> Self {
+        self.options.min_ring_nodes = min_ring_nodes;
+        self
+    }
+
+    pub fn warn_unclosed_distance(mut self, distance: f64) -> Self {
+        self.options.warn_unclosed_distance = distance;
+        self
+    }
+
+    /// Uses the members of the sub-relation members as if they were members of
+    /// the relation, recursively up to `max_depth` levels.
+    pub fn expand_subrelations(mut self, max_depth: usize) -> Self {
+        self.options.max_subrelation_depth = max_depth;
+        self
+    }
+
+    /// Closes the rings that are left open by a gap shorter than `tolerance`
+    /// meters (e.g. because of a missing way), each repair being reported
+    /// as a `BoundaryIssue::RepairedGap`.
+    pub fn repair_gaps(mut self, tolerance: f64) -> Self {
+        self.options.repair_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Reports the self-intersecting rings as `BoundaryIssue::SelfIntersection`,
+    /// and the other OGC validity problems of the boundary (orientation,
+    /// hole outside its shell, overlapping shells) as
+    /// `BoundaryIssue::InvalidGeometry`. The orientation is not checked when
+    /// a `winding` is set.
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.options.validate = validate;
+        self
+    }
+
+    /// Makes the invalid boundaries valid, by splitting the self-intersecting
+    /// rings and merging the overlapping polygons, see `make_valid`. The
+    /// boundaries whose only problem is the orientation of their rings are
+    /// just reoriented.
+    pub fn make_valid(mut self, make_valid: bool) -> Self {
+        self.options.make_valid = make_valid;
+        self
+    }
+
+    /// Orients the exterior rings along `winding`, and the interiors the
+    /// other way round.
+    pub fn winding(mut self, winding: RingWinding) -> Self {
+        self.options.winding = Some(winding);
+        self
+    }
+
+    /// Sets how the boundaries having rings crossing the antimeridian (which
+    /// are always reported as `BoundaryIssue::AntimeridianCrossing`) are
+    /// built. Their rings are shifted before the inner rings are matched
+    /// with the outer ones, so that their containment can be checked.
+    /// `build_rings` shifts them but does not split them, as splitting
+    /// creates new vertices.
+    pub fn antimeridian(mut self, antimeridian: Antimeridian) -> Self {
+        self.options.antimeridian = antimeridian;
+        self
+    }
+
+    pub fn build<S: OsmObjectStore + ?Sized>(
+        &self,
+        relation: &osmpbfreader::Relation,
+        objects: &S,
+    ) -> BoundaryReport {
+        let options = &self.options;
+        let mut issues = vec![];
+        let boundary = self
+            .assemble(relation, objects, &mut issues)
+            .map(|groups| groups.iter().map(|g| g.to_polygon()).collect())
+            .map(|multipolygon| match options.antimeridian {
+                Antimeridian::Split => split_antimeridian(multipolygon),
+                _ => multipolygon,
+            });
+        let orient = |multipolygon: MultiPolygon<f64>| {
+            use geo::orient::{Direction, Orient};
+            match options.winding {
+                Some(RingWinding::CounterClockwise) => multipolygon.orient(Direction::Default),
+                Some(RingWinding::Clockwise) => multipolygon.orient(Direction::Reversed),
+                None => multipolygon,
+            }
+        };
+        let boundary = boundary.map(orient).map(|multipolygon| {
+            if !options.validate && !options.make_valid {
+                return multipolygon;
+            }
+            let invalidities = validity_issues(&multipolygon, options);
+            if options.validate {
+                issues.extend(
+                    invalidities
+                        .iter()
+                        .filter(|i| !matches!(i, ValidityIssue::SelfIntersection { .. }))
+                        .cloned()
+                        .map(BoundaryIssue::InvalidGeometry),
+                );
+            }
+            let misoriented_only = invalidities
+                .iter()
+                .all(|i| matches!(i, ValidityIssue::WrongOrientation { .. }));
+            if !options.make_valid || invalidities.is_empty() {
+                multipolygon
+            } else if misoriented_only {
+                // no need for a union to fix the orientation of the rings
+                use geo::orient::{Direction, Orient};
+                orient(multipolygon.orient(Direction::Default))
+            } else {
+                orient(make_valid(&multipolygon))
+            }
+        });
+        BoundaryReport { boundary, issues }
+    }
+
+    /// Same as `build`, but keeps the id of the node of each vertex and of
+    /// the way of each segment. The winding and validation options apply,
+    /// but not `make_valid` as it creates new vertices.
+    pub fn build_rings<S: OsmObjectStore + ?Sized>(
+        &self,
+        relation: &osmpbfreader::Relation,
+        objects: &S,
+    ) -> BoundaryReport<BoundaryRings> {
+        use geo::winding_order::Winding;
+
+        let options = &self.options;
+        let mut issues = vec![];
+        let boundary = self
+            .assemble(relation, objects, &mut issues)
+            .map(|groups| BoundaryRings {
+                polygons: groups.into_iter().map(|g| g.into_traced()).collect(),
+            });
+        let boundary = boundary.map(|mut rings| {
+            if let Some(winding) = options.winding {
+                for polygon in &mut rings.polygons {
+                    let ccw = winding == RingWinding::CounterClockwise;
+                    if polygon.exterior.coords.is_ccw() != ccw {
+                        polygon.exterior.reverse();
+                    }
+                    for interior in &mut polygon.interiors {
+                        if interior.coords.is_ccw() == ccw {
+                            interior.reverse();
+                        }
                     }
                 }
+            }
+            if options.validate {
+                issues.extend(
+                    validity_issues(&rings.to_multipolygon(), options)
+                        .into_iter()
+                        .filter(|i| !matches!(i, ValidityIssue::SelfIntersection { .. }))
+                        .map(BoundaryIssue::InvalidGeometry),
+                );
+            }
+            rings
+        });
+        BoundaryReport { boundary, issues }
+    }
+
+    /// Builds the rings of the boundary, each outer with its inners.
+    fn assemble<S: OsmObjectStore + ?Sized>(
+        &self,
+        relation: &osmpbfreader::Relation,
+        objects: &S,
+        issues: &mut Vec<BoundaryIssue>,
+    ) -> Result<Vec<RingGroup>, BoundaryError> {
+        let options = &self.options;
+        let members = expand_members(relation, objects, options, issues);
+        if options.ignore_roles {
+            let roles: Vec<String> = options
+                .outer_roles
+                .iter()
+                .chain(options.inner_roles.iter())
+                .cloned()
+                .collect();
+            build_boundary_parts(relation, &members, objects, &roles, options, issues).map(
+                |mut rings| {
+                    shift_antimeridian(rings.iter_mut().collect(), options, issues);
+                    assemble_by_nesting(&members, rings, options, issues)
+                },
+            )
+        } else {
+            let mut outer_rings = build_boundary_parts(
+                relation,
+                &members,
+                objects,
+                &options.outer_roles,
+                options,
+                issues,
+            );
+            let mut inner_rings = build_boundary_parts(
+                relation,
+                &members,
+                objects,
+                &options.inner_roles,
+                options,
+                issues,
+            );
+            let rings = outer_rings
+                .iter_mut()
+                .chain(inner_rings.iter_mut())
+                .flatten()
+                .collect();
+            shift_antimeridian(rings, options, issues);
+            outer_rings.map(|outers| {
+                let mut groups: Vec<RingGroup> = outers
+                    .into_iter()
+                    .map(|outer| RingGroup {
+                        outer,
+                        inners: vec![],
+                    })
+                    .collect();
+                if let Ok(inners) = inner_rings {
+                    assign_inners(&mut groups, inners, options, issues);
+                }
+                groups
             })
         }
     }
-    outer_polys
 }
 
-pub fn build_boundary_parts<T: Borrow<osmpbfreader::OsmObj>>(
+/// Reports the rings crossing the antimeridian, i.e. having a segment
+/// spanning more than 180 degrees of longitude. If any, and unless the
+/// longitudes are to be kept, unwraps the longitudes of these rings to make
+/// them continuous, and shifts the rings of the western hemisphere by 360
+/// degrees.
+fn shift_antimeridian(
+    rings: Vec<&mut Ring>,
+    options: &BoundaryOptions,
+    issues: &mut Vec<BoundaryIssue>,
+) {
+    let crosses = |ring: &Ring| {
+        let next = ring.nodes.iter().skip(1).chain(ring.nodes.first());
+        ring.nodes
+            .iter()
+            .zip(next)
+            .any(|(a, b)| (a.coord.x - b.coord.x).abs() > 180.)
+    };
+    let mut crossing = false;
+    for ring in &rings {
+        if crosses(ring) {
+            issues.push(BoundaryIssue::AntimeridianCrossing {
+                node: ring.nodes[0].id,
+            });
+            crossing = true;
+        }
+    }
+    if !crossing || options.antimeridian == Antimeridian::Keep {
+        return;
+    }
+    for ring in rings {
+        let mut offset = 0.;
+        let mut previous = ring.nodes[0].coord.x;
+        for vertex in ring.nodes.iter_mut().skip(1) {
+            let lon = vertex.coord.x;
+            if lon - previous > 180. {
+                offset -= 360.;
+            } else if previous - lon > 180. {
+                offset += 360.;
+            }
+            previous = lon;
+            vertex.coord.x += offset;
+        }
+        let min_lon = ring
+            .nodes
+            .iter()
+            .map(|n| n.coord.x)
+            .fold(f64::INFINITY, f64::min);
+        if min_lon < 0. {
+            for vertex in &mut ring.nodes {
+                vertex.coord.x += 360.;
+            }
+        }
+    }
+}
+
+/// Splits a boundary shifted by `shift_antimeridian` along the
+/// antimeridian, shifting back its western part.
+fn split_antimeridian(multipolygon: MultiPolygon<f64>) -> MultiPolygon<f64> {
+    use geo::{BooleanOps, BoundingRect, MapCoords};
+
+    if !multipolygon
+        .bounding_rect()
+        .is_some_and(|rect| rect.max().x > 180.)
+    {
+        return multipolygon;
+    }
+    let east = Rect::new((-180., -90.), (180., 90.)).to_polygon();
+    let west = Rect::new((180., -90.), (540., 90.)).to_polygon();
+    let mut split = multipolygon.intersection(&MultiPolygon(vec![east]));
+    let western = multipolygon
+        .intersection(&MultiPolygon(vec![west]))
+        .map_coords(|c| Coord {
+            x: c.x - 360.,
+            y: c.y,
+        });
+    split.0.extend(western.0);
+    split
+}
+
+/// Members of the relation, where the members of the sub-relations are
+/// expanded up to `options.max_subrelation_depth` levels.
+///
+/// The members of a sub-relation take the role of the sub-relation, except
+/// those with an inner role which take the opposite one, so that a
+/// multipolygon used as an inner still has its holes as outers.
+fn expand_members<S: OsmObjectStore + ?Sized>(
     relation: &osmpbfreader::Relation,
-    objects: &BTreeMap<osmpbfreader::OsmId, T>,
-    roles_to_extact: Vec<&str>,
-) -> Option<MultiPolygon<f64>> {
-    let roles = roles_to_extact;
-    let mut boundary_parts: Vec<BoundaryPart> = relation
-        .refs
+    objects: &S,
+    options: &BoundaryOptions,
+    issues: &mut Vec<BoundaryIssue>,
+) -> Vec<osmpbfreader::Ref> {
+    if options.max_subrelation_depth == 0 {
+        return relation.refs.clone();
+    }
+    let mut members = vec![];
+    let mut path = vec![relation.id];
+    let mut expanded = BTreeSet::new();
+    expand_sub_members(
+        relation,
+        None,
+        objects,
+        options,
+        &mut path,
+        &mut expanded,
+        &mut members,
+        issues,
+    );
+    members
+}
+
+/// OGC validity problems of a boundary. The orientation of the rings is not
+/// checked when a winding is set, as the rings follow it rather than the
+/// counter-clockwise exteriors of the OGC rules.
+fn validity_issues(
+    multipolygon: &MultiPolygon<f64>,
+    options: &BoundaryOptions,
+) -> Vec<ValidityIssue> {
+    let mut issues = validate_multipolygon(multipolygon);
+    if options.winding.is_some() {
+        issues.retain(|i| !matches!(i, ValidityIssue::WrongOrientation { .. }));
+    }
+    issues
+}
+
+#[allow(clippy::too_many_arguments)]
+fn expand_sub_members<S: OsmObjectStore + ?Sized>(
+    relation: &osmpbfreader::Relation,
+    relation_role: Option<&str>,
+    objects: &S,
+    options: &BoundaryOptions,
+    path: &mut Vec<osmpbfreader::RelationId>,
+    expanded: &mut BTreeSet<osmpbfreader::RelationId>,
+    members: &mut Vec<osmpbfreader::Ref>,
+    issues: &mut Vec<BoundaryIssue>,
+) {
+    let is_inner = |role: &str| options.inner_roles.iter().any(|r| r == role);
+    let is_outer = |role: &str| options.outer_roles.iter().any(|r| r == role);
+    let first_role = |roles: &[String]| roles.first().cloned().unwrap_or_default();
+    for r in &relation.refs {
+        let role = match relation_role {
+            None => r.role.to_string(),
+            // `subarea`, `label`... members of a sub-relation are not part
+            // of its outline
+            Some(_) if !is_outer(&r.role) && !is_inner(&r.role) => continue,
+            Some(parent) if is_inner(&r.role) => {
+                if is_inner(parent) {
+                    first_role(&options.outer_roles)
+                } else {
+                    first_role(&options.inner_roles)
+                }
+            }
+            Some(parent) => parent.to_string(),
+        };
+        let id = match r.member {
+            osmpbfreader::OsmId::Relation(id) if is_outer(&role) || is_inner(&role) => id,
+            _ => {
+                members.push(osmpbfreader::Ref {
+                    member: r.member,
+                    role: role.into(),
+                });
+                continue;
+            }
+        };
+        if path.contains(&id) {
+            issues.push(BoundaryIssue::RelationCycle(id));
+        } else if path.len() > options.max_subrelation_depth {
+            issues.push(BoundaryIssue::TooDeepRelation(id));
+        } else if !expanded.insert(id) {
+            debug!(
+                "relation {} already expanded in relation {}",
+                id.0, path[0].0
+            );
+        } else {
+            match objects.relation(id) {
+                Some(sub_relation) => {
+                    path.push(id);
+                    expand_sub_members(
+                        &sub_relation,
+                        Some(&role),
+                        objects,
+                        options,
+                        path,
+                        expanded,
+                        members,
+                        issues,
+                    );
+                    path.pop();
+                }
+                None => issues.push(BoundaryIssue::MissingRelation(id)),
+            }
+        }
+    }
+}
+
+/// Splits rings into outers and inners according to how many rings enclose
+/// them, reporting the ways whose role disagrees.
+fn assemble_by_nesting(
+    members: &[osmpbfreader::Ref],
+    rings: Vec<Ring>,
+    options: &BoundaryOptions,
+    issues: &mut Vec<BoundaryIssue>,
+) -> Vec<RingGroup> {
+    use geo::prelude::Area;
+
+    let polygons: Vec<(Polygon<f64>, f64)> = rings
         .iter()
-        .filter(|r| roles.contains(&r.role.as_str()))
-        .filter_map(|r| {
-            let obj = objects.get(&r.member);
-            if obj.is_none() {
-                debug!(
-                    "missing element {:?} for relation {}",
-                    r.member, relation.id.0
-                );
+        .map(|ring| {
+            let polygon = Polygon::new(ring.to_line_string(), vec![]);
+            let area = polygon.unsigned_area();
+            (polygon, area)
+        })
+        .collect();
+
+    let mut outers = vec![];
+    let mut inners = vec![];
+    for (ring, (polygon, area)) in rings.into_iter().zip(polygons.iter()) {
+        // Comparing areas prevents two identical rings from enclosing each other
+        let depth = polygons
+            .iter()
+            .filter(|(other, other_area)| other_area > area && encloses(other, polygon.exterior()))
+            .count();
+        let computed = if depth % 2 == 0 {
+            RingRole::Outer
+        } else {
+            RingRole::Inner
+        };
+
+        let mut ways: Vec<_> = ring.ways.iter().flatten().cloned().collect();
+        ways.sort();
+        ways.dedup();
+        for way in ways {
+            let member = members
+                .iter()
+                .find(|r| r.member == osmpbfreader::OsmId::Way(way));
+            if let Some(member) = member {
+                let tagged = if options
+                    .inner_roles
+                    .iter()
+                    .any(|r| r == member.role.as_str())
+                {
+                    RingRole::Inner
+                } else {
+                    RingRole::Outer
+                };
+                if tagged != computed {
+                    issues.push(BoundaryIssue::WrongRole {
+                        way,
+                        role: member.role.to_string(),
+                        computed,
+                    });
+                }
             }
-            obj
+        }
+
+        match computed {
+            RingRole::Outer => outers.push(RingGroup {
+                outer: ring,
+                inners: vec![],
+            }),
+            RingRole::Inner => inners.push(ring),
+        }
+    }
+    assign_inners(&mut outers, inners, options, issues);
+    outers
+}
+
+/// Adds each inner ring as a hole of the outer ring enclosing it.
+fn assign_inners(
+    outers: &mut [RingGroup],
+    inners: Vec<Ring>,
+    options: &BoundaryOptions,
+    issues: &mut Vec<BoundaryIssue>,
+) {
+    use geo::prelude::Area;
+
+    /*
+        Outers may be nested (e.g. an island in a lake of a boundary, itself
+        having a pond), in which case the 'inner' ring is enclosed by several
+        concentric outers: it belongs to the smallest of them.
+        The test is done on the exteriors only, so that holes already added to
+        an outer do not hide the rings nested in them.
+    */
+    let exteriors: Vec<(Polygon<f64>, f64)> = outers
+        .iter()
+        .map(|group| {
+            let exterior = Polygon::new(group.outer.to_line_string(), vec![]);
+            let area = exterior.unsigned_area();
+            (exterior, area)
         })
-        .filter_map(|way_obj| way_obj.borrow().way())
-        .map(|way| get_nodes(way, objects))
-        .filter_map(BoundaryPart::new)
         .collect();
-    let mut multipoly = MultiPolygon(vec![]);
 
-    let mut append_ring = |nodes: &[osmpbfreader::Node]| {
-        let poly_geom = nodes
+    for inner in inners {
+        let node = inner.nodes[0].id;
+        let ring = inner.to_line_string();
+        let mut enclosing = exteriors
             .iter()
-            .map(|n| Coord {
-                x: n.lon(),
-                y: n.lat(),
-            })
-            .collect();
-        multipoly
-            .0
-            .push(Polygon::new(LineString(poly_geom), vec![]));
+            .enumerate()
+            .filter(|(_, (exterior, _))| encloses(exterior, &ring));
+        let enclosing = match options.inner_matching {
+            InnerMatching::SmallestEnclosing => {
+                enclosing.min_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
+            }
+            InnerMatching::FirstEnclosing => enclosing.next(),
+        }
+        .map(|(idx, _)| idx);
+        match enclosing {
+            Some(idx) => outers[idx].inners.push(inner),
+            None => issues.push(BoundaryIssue::UnassignedInner { node }),
+        }
+    }
+}
+
+/// Angle, in radians between -π and π, by which the direction changes at `b`
+/// when going from `a` to `c`, positive for a left turn.
+fn turning_angle(a: &Vertex, b: &Vertex, c: &Vertex) -> f64 {
+    use std::f64::consts::PI;
+    let incoming = (b.coord.y - a.coord.y).atan2(b.coord.x - a.coord.x);
+    let outgoing = (c.coord.y - b.coord.y).atan2(c.coord.x - b.coord.x);
+    let angle = outgoing - incoming;
+    if angle > PI {
+        angle - 2. * PI
+    } else if angle < -PI {
+        angle + 2. * PI
+    } else {
+        angle
+    }
+}
+
+/// Among the remaining parts touching the end of `last_segment`, the one
+/// going the straightest on, the smallest way id breaking ties, so that the
+/// rings do not depend on the members order when more than two ways meet.
+fn next_part(
+    candidates: &mut Vec<usize>,
+    parts: &[Option<BoundaryPart>],
+    last_segment: (Vertex, Vertex),
+) -> Option<usize> {
+    candidates.retain(|&i| parts[i].is_some());
+    if candidates.len() <= 1 {
+        return candidates.first().cloned();
+    }
+    let (previous, current) = last_segment;
+    let turn = |i: usize| {
+        let part = parts[i].as_ref().unwrap();
+        let nodes = part.nodes();
+        let next = if part.first() == current.id {
+            &nodes[1]
+        } else {
+            &nodes[nodes.len() - 2]
+        };
+        (turning_angle(&previous, &current, next).abs(), part.way())
     };
+    candidates.iter().cloned().min_by(|&i, &j| {
+        let (angle_i, way_i) = turn(i);
+        let (angle_j, way_j) = turn(j);
+        angle_i
+            .partial_cmp(&angle_j)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(way_i.cmp(&way_j))
+    })
+}
 
-    while !boundary_parts.is_empty() {
-        let first_part = boundary_parts.remove(0);
-        let mut added_nodes: Vec<osmpbfreader::Node> = vec![];
-        let mut node_to_idx: BTreeMap<osmpbfreader::NodeId, usize> = BTreeMap::new();
+/// Chains the parts greedily, pushing the closed rings to `rings` and
+/// returning the chains that could not be closed.
+fn chain_parts(
+    relation: &osmpbfreader::Relation,
+    mut boundary_parts: Vec<BoundaryPart>,
+    options: &BoundaryOptions,
+    rings: &mut Vec<Ring>,
+    issues: &mut Vec<BoundaryIssue>,
+) -> Vec<Chain> {
+    // the parts are chained in an order not depending on the members order
+    boundary_parts.sort_by_key(|part| part.way());
+
+    // parts touching each node, by their index in boundary_parts
+    let mut parts_by_end: HashMap<osmpbfreader::NodeId, Vec<usize>> = HashMap::new();
+    for (i, part) in boundary_parts.iter().enumerate() {
+        parts_by_end.entry(part.first()).or_default().push(i);
+        if part.last() != part.first() {
+            parts_by_end.entry(part.last()).or_default().push(i);
+        }
+    }
+    let mut boundary_parts: Vec<Option<BoundaryPart>> =
+        boundary_parts.into_iter().map(Some).collect();
+
+    let mut chains = vec![];
+
+    for first_idx in 0..boundary_parts.len() {
+        let first_part = match boundary_parts[first_idx].take() {
+            Some(part) => part,
+            None => continue,
+        };
+        let mut added_nodes: Vec<Vertex> = vec![];
+        // way that led to each added node
+        let mut added_ways: Vec<Option<osmpbfreader::WayId>> = vec![];
+        let mut node_to_idx: HashMap<osmpbfreader::NodeId, usize> = HashMap::new();
 
         let mut add_part = |part: BoundaryPart| {
+            let way = Some(part.way());
             let mut part = part.into_vec();
 
             let nodes = if added_nodes.is_empty() {
@@ -211,100 +1175,697 @@ pub fn build_boundary_parts<T: Borrow<osmpbfreader::OsmObj>>(
             };
 
             for n in nodes {
+                let mut way_to_n = way;
                 if let Some(start_idx) = node_to_idx.get(&n.id) {
                     let ring = added_nodes.split_off(*start_idx);
-                    node_to_idx = added_nodes
-                        .iter()
-                        .enumerate()
-                        .map(|(i, n)| (n.id, i))
-                        .collect();
-                    if ring.len() >= 3 {
-                        append_ring(&ring);
+                    let mut ways = added_ways.split_off(*start_idx + 1);
+                    ways.push(way);
+                    // n is added back below, as reached by the way leading to the ring start
+                    way_to_n = added_ways.pop().unwrap();
+                    for node in &ring {
+                        node_to_idx.remove(&node.id);
+                    }
+                    if ring.len() >= options.min_ring_nodes {
+                        rings.push(Ring { nodes: ring, ways });
                     } else {
                         debug!(
-                            "Ignored ring with less than 3 nodes in relation:{} at node:{}",
-                            relation.id.0, n.id.0
+                            "Ignored ring with less than {} nodes in relation:{} at node:{}",
+                            options.min_ring_nodes, relation.id.0, n.id.0
                         );
+                        issues.push(BoundaryIssue::DegenerateRing { node: n.id });
                     }
                 }
                 node_to_idx.insert(n.id, added_nodes.len());
                 added_nodes.push(n);
+                added_ways.push(way_to_n);
             }
         };
 
-        let mut current = first_part.last();
+        let mut last_segment = first_part.last_segment();
         add_part(first_part);
 
-        loop {
-            let mut added_part = false;
-            let mut i = 0;
-            while i < boundary_parts.len() {
-                if current == boundary_parts[i].first() {
-                    // the start of current way touches the polygon,
-                    // we add it and remove it from the pool
-                    current = boundary_parts[i].last();
-                    add_part(boundary_parts.remove(i));
-                    added_part = true;
-                } else if current == boundary_parts[i].last() {
-                    // the end of the current way touches the polygon, we reverse the way and add it
-                    current = boundary_parts[i].first();
-                    boundary_parts[i].reverse();
-                    add_part(boundary_parts.remove(i));
-                    added_part = true;
-                } else {
-                    i += 1;
-                    // didn't do anything, we want to explore the next way, if we had do something we
-                    // will have removed the current way and there will be no need to increment
-                }
+        // a remaining part touching the current end is added, reversed if
+        // needed, until no part touches it
+        while let Some(i) = parts_by_end
+            .get_mut(&last_segment.1.id)
+            .and_then(|candidates| next_part(candidates, &boundary_parts, last_segment))
+        {
+            let mut part = boundary_parts[i].take().unwrap();
+            if last_segment.1.id != part.first() {
+                part.reverse();
             }
-            if !added_part {
-                use geo::haversine_distance::HaversineDistance;
-                let p = |n: &osmpbfreader::Node| {
-                    Point(Coord {
-                        x: n.lon(),
-                        y: n.lat(),
-                    })
-                };
+            last_segment = part.last_segment();
+            add_part(part);
+        }
+        if added_nodes.len() > 1 {
+            chains.push(Chain {
+                ways: added_ways.split_off(1),
+                nodes: added_nodes,
+            });
+        }
+    }
+    chains
+}
 
-                if added_nodes.len() > 1 {
-                    let distance = p(added_nodes.first().unwrap())
-                        .haversine_distance(&p(added_nodes.last().unwrap()));
-                    if distance < WARN_UNCLOSED_RING_MAX_DISTANCE {
-                        warn!(
-                            "boundary: relation/{} ({}): unclosed polygon, dist({:?}, {:?}) = {}",
-                            relation.id.0,
-                            relation.tags.get("name").map_or("", |s| s),
-                            added_nodes.first().unwrap().id,
-                            added_nodes.last().unwrap().id,
-                            distance
-                        );
-                    }
-                }
-                break;
+/// Builds the planar graph of the segments of the parts, and pushes its
+/// bounded faces to `rings`. The dangling segments, which cannot be part of
+/// a face, are returned as chains.
+fn extract_faces(
+    relation: &osmpbfreader::Relation,
+    mut boundary_parts: Vec<BoundaryPart>,
+    options: &BoundaryOptions,
+    rings: &mut Vec<Ring>,
+    issues: &mut Vec<BoundaryIssue>,
+) -> Vec<Chain> {
+    // the faces are traced in an order not depending on the members order
+    boundary_parts.sort_by_key(|part| part.way());
+
+    let mut vertices: Vec<Vertex> = vec![];
+    let mut vertex_idx: HashMap<osmpbfreader::NodeId, usize> = HashMap::new();
+    // undirected segments, with the way they come from
+    let mut edges: BTreeMap<(usize, usize), osmpbfreader::WayId> = BTreeMap::new();
+    for part in &boundary_parts {
+        let idx: Vec<usize> = part
+            .nodes()
+            .iter()
+            .map(|n| {
+                *vertex_idx.entry(n.id).or_insert_with(|| {
+                    vertices.push(*n);
+                    vertices.len() - 1
+                })
+            })
+            .collect();
+        for w in idx.windows(2) {
+            if w[0] != w[1] {
+                let key = (w[0].min(w[1]), w[0].max(w[1]));
+                edges.entry(key).or_insert_with(|| part.way());
             }
         }
     }
-    if multipoly.0.is_empty() {
-        None
-    } else {
-        Some(multipoly)
+    let mut edges = split_crossings(&mut vertices, edges, issues);
+
+    let mut neighbors: Vec<Vec<usize>> = vec![vec![]; vertices.len()];
+    for &(a, b) in edges.keys() {
+        neighbors[a].push(b);
+        neighbors[b].push(a);
     }
-}
 
-#[test]
-fn test_build_boundary_empty() {
-    let objects: BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj> = BTreeMap::new();
-    let mut relation = osmpbfreader::Relation {
-        id: osmpbfreader::RelationId(12),
-        refs: vec![],
-        tags: osmpbfreader::Tags::new(),
+    // dangling segments are removed, leaf after leaf
+    let mut dangling: BTreeMap<(usize, usize), osmpbfreader::WayId> = BTreeMap::new();
+    let mut leaves: Vec<usize> = (0..vertices.len())
+        .filter(|&v| neighbors[v].len() == 1)
+        .collect();
+    while let Some(v) = leaves.pop() {
+        if let Some(u) = neighbors[v].pop() {
+            neighbors[u].retain(|&n| n != v);
+            let key = (u.min(v), u.max(v));
+            let way = edges.remove(&key).unwrap();
+            dangling.insert(key, way);
+            if neighbors[u].len() == 1 {
+                leaves.push(u);
+            }
+        }
+    }
+
+    // around each vertex, the neighbors are sorted counter-clockwise
+    let angle = |from: usize, to: usize| {
+        let (a, b) = (vertices[from].coord, vertices[to].coord);
+        (b.y - a.y).atan2(b.x - a.x)
     };
-    relation.refs.push(osmpbfreader::Ref {
-        member: osmpbfreader::WayId(4).into(),
-        role: "outer".into(),
-    });
-    relation.refs.push(osmpbfreader::Ref {
-        member: osmpbfreader::WayId(65).into(),
+    for (v, around) in neighbors.iter_mut().enumerate() {
+        around.sort_by(|&a, &b| {
+            angle(v, a)
+                .partial_cmp(&angle(v, b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.cmp(&b))
+        });
+    }
+
+    let mut faces = trace_faces(&vertices, &neighbors, &edges);
+    // the segments bordered by outer faces on both sides are inside the
+    // boundary: they are dropped, merging the faces, while the holes
+    // touching their outer ring are kept
+    let outer = outer_faces(&vertices, &faces);
+    let mut outer_sides: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for ((face, _), _) in faces.iter().zip(&outer).filter(|(_, &outer)| outer) {
+        for (k, &u) in face.iter().enumerate() {
+            let v = face[(k + 1) % face.len()];
+            *outer_sides.entry((u.min(v), u.max(v))).or_default() += 1;
+        }
+    }
+    let inside: Vec<(usize, usize)> = outer_sides
+        .into_iter()
+        .filter(|&(_, sides)| sides == 2)
+        .map(|(key, _)| key)
+        .collect();
+    if !inside.is_empty() {
+        for (a, b) in inside {
+            edges.remove(&(a, b));
+            neighbors[a].retain(|&n| n != b);
+            neighbors[b].retain(|&n| n != a);
+        }
+        faces = trace_faces(&vertices, &neighbors, &edges);
+    }
+
+    for (face, doubled_area) in faces {
+        if doubled_area <= 0. {
+            continue;
+        }
+        // the holes touching the face are faces of their own
+        let face = exterior_loop(&vertices, &face);
+        let nodes: Vec<Vertex> = face.iter().map(|&u| vertices[u]).collect();
+        if nodes.len() < options.min_ring_nodes {
+            debug!(
+                "Ignored ring with less than {} nodes in relation:{} at node:{}",
+                options.min_ring_nodes, relation.id.0, nodes[0].id.0
+            );
+            issues.push(BoundaryIssue::DegenerateRing { node: nodes[0].id });
+            continue;
+        }
+        let ways = (0..face.len())
+            .map(|k| {
+                let (u, v) = (face[k], face[(k + 1) % face.len()]);
+                Some(edges[&(u.min(v), u.max(v))])
+            })
+            .collect();
+        rings.push(Ring { nodes, ways });
+    }
+
+    dangling_chains(&vertices, dangling)
+}
+
+/// Splits the segments at their crossings, and at the vertices lying on
+/// them, so that the segments only meet at their ends. The vertices added
+/// at the crossings get negative ids, as they are not OSM nodes.
+fn split_crossings(
+    vertices: &mut Vec<Vertex>,
+    edges: BTreeMap<(usize, usize), osmpbfreader::WayId>,
+    issues: &mut Vec<BoundaryIssue>,
+) -> BTreeMap<(usize, usize), osmpbfreader::WayId> {
+    use geo::line_intersection::{line_intersection, LineIntersection};
+    use geo_types::Line;
+    use rstar::primitives::{GeomWithData, Line as Segment};
+    use rstar::{RTree, AABB};
+
+    let keys: Vec<(usize, usize)> = edges.keys().cloned().collect();
+    let lines: Vec<Line<f64>> = keys
+        .iter()
+        .map(|&(a, b)| Line::new(vertices[a].coord, vertices[b].coord))
+        .collect();
+    let tree: RTree<GeomWithData<Segment<[f64; 2]>, usize>> = RTree::bulk_load(
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, l)| GeomWithData::new(Segment::new(l.start.into(), l.end.into()), i))
+            .collect(),
+    );
+
+    // vertices inside each segment, by the index of the segment
+    let mut splits: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    let mut crossings: HashMap<(u64, u64), usize> = HashMap::new();
+    let mut crossing_ways = BTreeSet::new();
+    for (i, line) in lines.iter().enumerate() {
+        let envelope = AABB::from_corners(line.start.into(), line.end.into());
+        let (a, b) = keys[i];
+        for j in tree
+            .locate_in_envelope_intersecting(&envelope)
+            .map(|segment| segment.data)
+            .filter(|&j| j > i)
+        {
+            let (c, d) = keys[j];
+            if a == c || a == d || b == c || b == d {
+                continue;
+            }
+            // (segment, vertex) splits, a vertex only splitting a segment
+            // it is strictly inside of
+            let mut found = vec![];
+            let mut crossing = false;
+            match line_intersection(*line, lines[j]) {
+                None => continue,
+                Some(LineIntersection::SinglePoint {
+                    intersection,
+                    is_proper: true,
+                }) => {
+                    let key = (intersection.x.to_bits(), intersection.y.to_bits());
+                    let id = osmpbfreader::NodeId(-(crossings.len() as i64) - 1);
+                    let v = *crossings.entry(key).or_insert_with(|| {
+                        vertices.push(Vertex {
+                            id,
+                            coord: intersection,
+                        });
+                        vertices.len() - 1
+                    });
+                    found.push((i, v));
+                    found.push((j, v));
+                    crossing = true;
+                }
+                Some(LineIntersection::SinglePoint { intersection, .. }) => {
+                    found.extend(
+                        [(j, a), (j, b)]
+                            .iter()
+                            .filter(|&&(_, v)| vertices[v].coord == intersection),
+                    );
+                    found.extend(
+                        [(i, c), (i, d)]
+                            .iter()
+                            .filter(|&&(_, v)| vertices[v].coord == intersection),
+                    );
+                }
+                Some(LineIntersection::Collinear { intersection }) => {
+                    let ends = |v: usize| {
+                        vertices[v].coord == intersection.start
+                            || vertices[v].coord == intersection.end
+                    };
+                    found.extend([(j, a), (j, b)].iter().filter(|&&(_, v)| ends(v)));
+                    found.extend([(i, c), (i, d)].iter().filter(|&&(_, v)| ends(v)));
+                }
+            }
+            found.retain(|&(segment, v)| {
+                let line = lines[segment];
+                vertices[v].coord != line.start && vertices[v].coord != line.end
+            });
+            if found.is_empty() {
+                continue;
+            }
+            for (segment, v) in found {
+                splits.entry(segment).or_default().push(v);
+            }
+            if crossing {
+                let (way, other) = (edges[&keys[i]], edges[&keys[j]]);
+                crossing_ways.insert((way.min(other), way.max(other)));
+            }
+        }
+    }
+    issues.extend(
+        crossing_ways
+            .into_iter()
+            .map(|(way, other)| BoundaryIssue::CrossingWays { way, other }),
+    );
+    if splits.is_empty() {
+        return edges;
+    }
+
+    let mut split_edges = BTreeMap::new();
+    for (i, &(a, b)) in keys.iter().enumerate() {
+        let mut path = vec![a];
+        if let Some(inside) = splits.get_mut(&i) {
+            let start = vertices[a].coord;
+            let distance = |v: usize| {
+                let d = vertices[v].coord - start;
+                d.x * d.x + d.y * d.y
+            };
+            inside.sort_by(|&u, &v| distance(u).total_cmp(&distance(v)));
+            inside.dedup();
+            path.extend(inside.iter().cloned());
+        }
+        path.push(b);
+        for w in path.windows(2) {
+            if w[0] != w[1] {
+                let key = (w[0].min(w[1]), w[0].max(w[1]));
+                split_edges.entry(key).or_insert(edges[&(a, b)]);
+            }
+        }
+    }
+    split_edges
+}
+
+/// Faces of a planar graph, as their vertices with their doubled signed
+/// area. Each face is traced by turning as much as possible to the right at
+/// each vertex, which keeps the face on the left: bounded faces are then
+/// counter-clockwise, with a positive area.
+fn trace_faces(
+    vertices: &[Vertex],
+    neighbors: &[Vec<usize>],
+    edges: &BTreeMap<(usize, usize), osmpbfreader::WayId>,
+) -> Vec<(Vec<usize>, f64)> {
+    let mut faces = vec![];
+    let mut visited: BTreeSet<(usize, usize)> = BTreeSet::new();
+    for &(a, b) in edges.keys() {
+        for &start in &[(a, b), (b, a)] {
+            if visited.contains(&start) {
+                continue;
+            }
+            let mut face = vec![];
+            let mut doubled_area = 0.;
+            let (mut u, mut v) = start;
+            loop {
+                visited.insert((u, v));
+                face.push(u);
+                let (cu, cv) = (vertices[u].coord, vertices[v].coord);
+                doubled_area += cu.x * cv.y - cv.x * cu.y;
+
+                let around = &neighbors[v];
+                let k = around.iter().position(|&n| n == u).unwrap();
+                let w = around[(k + around.len() - 1) % around.len()];
+                u = v;
+                v = w;
+                if (u, v) == start {
+                    break;
+                }
+            }
+            faces.push((face, doubled_area));
+        }
+    }
+    faces
+}
+
+/// Splits the walk around a face at the vertices it goes through several
+/// times, into simple loops with their doubled signed area.
+fn simple_loops(vertices: &[Vertex], face: &[usize]) -> Vec<(Vec<usize>, f64)> {
+    let mut loops = vec![];
+    let mut path = vec![];
+    let mut position: HashMap<usize, usize> = HashMap::new();
+    for &v in face {
+        match position.get(&v) {
+            Some(&k) => {
+                let walk = path.split_off(k);
+                for u in &walk[1..] {
+                    position.remove(u);
+                }
+                path.push(v);
+                loops.push(walk);
+            }
+            None => {
+                position.insert(v, path.len());
+                path.push(v);
+            }
+        }
+    }
+    loops.push(path);
+    loops
+        .into_iter()
+        .map(|walk| {
+            let doubled_area = (0..walk.len())
+                .map(|k| {
+                    let a = vertices[walk[k]].coord;
+                    let b = vertices[walk[(k + 1) % walk.len()]].coord;
+                    a.x * b.y - b.x * a.y
+                })
+                .sum();
+            (walk, doubled_area)
+        })
+        .collect()
+}
+
+/// Outer ring of a bounded face, i.e. its walk without the holes touching it.
+fn exterior_loop(vertices: &[Vertex], face: &[usize]) -> Vec<usize> {
+    simple_loops(vertices, face)
+        .into_iter()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(walk, _)| walk)
+        .unwrap_or_default()
+}
+
+/// Whether each face is an outer one, i.e. a bounded face lying in the
+/// exterior ring of an even number of other faces. As the faces do not
+/// overlap, a face can only lie in the exterior ring of another one by
+/// being in one of its holes.
+fn outer_faces(vertices: &[Vertex], faces: &[(Vec<usize>, f64)]) -> Vec<bool> {
+    use geo::prelude::Area;
+    use geo::BoundingRect;
+
+    let exteriors: Vec<_> = faces
+        .iter()
+        .map(|(face, doubled_area)| {
+            if *doubled_area <= 0. {
+                return None;
+            }
+            let line_string: LineString<f64> = exterior_loop(vertices, face)
+                .iter()
+                .map(|&u| vertices[u].coord)
+                .collect();
+            let polygon = Polygon::new(line_string, vec![]);
+            let area = polygon.unsigned_area();
+            let rect = polygon.bounding_rect()?;
+            Some((polygon, area, rect))
+        })
+        .collect();
+    exteriors
+        .iter()
+        .map(|exterior| {
+            let (polygon, area, rect) = match exterior {
+                Some(exterior) => exterior,
+                None => return false,
+            };
+            let depth = exteriors
+                .iter()
+                .flatten()
+                .filter(|(other, other_area, other_rect)| {
+                    other_area > area
+                        && other_rect.min().x <= rect.min().x
+                        && other_rect.min().y <= rect.min().y
+                        && other_rect.max().x >= rect.max().x
+                        && other_rect.max().y >= rect.max().y
+                        && is_within(polygon, other)
+                })
+                .count();
+            depth % 2 == 0
+        })
+        .collect()
+}
+
+/// Splits the dangling segments, which make trees, into chains going from a
+/// leaf to the next leaf or junction.
+fn dangling_chains(
+    vertices: &[Vertex],
+    mut segments: BTreeMap<(usize, usize), osmpbfreader::WayId>,
+) -> Vec<Chain> {
+    let mut neighbors: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for &(a, b) in segments.keys() {
+        neighbors.entry(a).or_default().push(b);
+        neighbors.entry(b).or_default().push(a);
+    }
+    let mut chains = vec![];
+    while let Some(&start) = neighbors
+        .iter()
+        .find(|(_, around)| around.len() == 1)
+        .map(|(v, _)| v)
+    {
+        let mut nodes = vec![vertices[start]];
+        let mut ways = vec![];
+        let mut v = start;
+        while let Some(next) = neighbors.get_mut(&v).and_then(|around| around.pop()) {
+            let around = neighbors.get_mut(&next).unwrap();
+            around.retain(|&n| n != v);
+            ways.push(segments.remove(&(v.min(next), v.max(next))));
+            nodes.push(vertices[next]);
+            v = next;
+            if neighbors[&v].len() != 1 {
+                break;
+            }
+        }
+        neighbors.retain(|_, around| !around.is_empty());
+        chains.push(Chain { nodes, ways });
+    }
+    chains
+}
+
+/// Chains the ways of the members having one of the given roles into closed
+/// rings, bridging the gaps left between the chains that do not close if
+/// `options.repair_tolerance` is set.
+fn build_boundary_parts<S: OsmObjectStore + ?Sized>(
+    relation: &osmpbfreader::Relation,
+    members: &[osmpbfreader::Ref],
+    objects: &S,
+    roles: &[String],
+    options: &BoundaryOptions,
+    issues: &mut Vec<BoundaryIssue>,
+) -> Result<Vec<Ring>, BoundaryError> {
+    let members: Vec<&osmpbfreader::Ref> = members
+        .iter()
+        .filter(|r| roles.iter().any(|role| role == r.role.as_str()))
+        .collect();
+    if members.is_empty() {
+        return Err(BoundaryError::NoMember);
+    }
+
+    let mut boundary_parts: Vec<BoundaryPart> = vec![];
+    let mut has_way_member = false;
+    let mut has_way = false;
+    for r in members {
+        let id = match r.member {
+            osmpbfreader::OsmId::Way(id) => id,
+            _ => continue,
+        };
+        has_way_member = true;
+        match objects.way(id) {
+            Some(way) => {
+                has_way = true;
+                boundary_parts.extend(BoundaryPart::new(id, get_nodes(&way, objects, issues)))
+            }
+            None => {
+                debug!("missing way {} for relation {}", id.0, relation.id.0);
+                issues.push(BoundaryIssue::MissingWay(id));
+            }
+        }
+    }
+    if !has_way_member {
+        return Err(BoundaryError::NoWayMember);
+    } else if !has_way {
+        return Err(BoundaryError::MissingMembers);
+    } else if boundary_parts.is_empty() {
+        return Err(BoundaryError::MissingNodes);
+    }
+    // position of the first member of each way
+    let mut member_positions: HashMap<osmpbfreader::WayId, usize> = HashMap::new();
+    for (position, part) in boundary_parts.iter().enumerate() {
+        member_positions.entry(part.way()).or_insert(position);
+    }
+
+    let mut rings = vec![];
+    let mut chains = match options.ring_assembly {
+        RingAssembly::Chaining => {
+            chain_parts(relation, boundary_parts, options, &mut rings, issues)
+        }
+        RingAssembly::Faces => extract_faces(relation, boundary_parts, options, &mut rings, issues),
+    };
+
+    if let Some(tolerance) = options.repair_tolerance {
+        chains = repair_gaps(relation, chains, tolerance, options, &mut rings, issues);
+    }
+    // the rings are built in the way ids order, but returned in the members
+    // order, of the first member of each ring
+    rings.sort_by_key(|ring| {
+        ring.ways
+            .iter()
+            .flatten()
+            .filter_map(|way| member_positions.get(way))
+            .min()
+            .cloned()
+    });
+    let has_unclosed_ring = !chains.is_empty();
+    for chain in chains {
+        let first = chain.nodes.first().unwrap();
+        let last = chain.nodes.last().unwrap();
+        let distance = distance(first, last);
+        if distance < options.warn_unclosed_distance {
+            warn!(
+                "boundary: relation/{} ({}): unclosed polygon, dist({:?}, {:?}) = {}",
+                relation.id.0,
+                relation.tags.get("name").map_or("", |s| s),
+                first.id,
+                last.id,
+                distance
+            );
+        }
+        issues.push(BoundaryIssue::UnclosedRing {
+            first: first.id,
+            last: last.id,
+            distance,
+        });
+    }
+
+    if options.validate {
+        for ring in &rings {
+            if is_self_intersecting(&ring.to_line_string()) {
+                issues.push(BoundaryIssue::SelfIntersection {
+                    node: ring.nodes[0].id,
+                });
+            }
+        }
+    }
+
+    if !rings.is_empty() {
+        Ok(rings)
+    } else if has_unclosed_ring {
+        Err(BoundaryError::UnclosedRing)
+    } else {
+        Err(BoundaryError::DegenerateRings)
+    }
+}
+
+/// Bridges the gaps shorter than `tolerance` meters between the ends of the
+/// chains with straight segments, pushing the rings closed this way into
+/// `rings`. Each chain end is joined to the nearest chain end in reach, which
+/// may be its own other end. Returns the chains that are still unclosed.
+fn repair_gaps(
+    relation: &osmpbfreader::Relation,
+    mut chains: Vec<Chain>,
+    tolerance: f64,
+    options: &BoundaryOptions,
+    rings: &mut Vec<Ring>,
+    issues: &mut Vec<BoundaryIssue>,
+) -> Vec<Chain> {
+    let mut unclosed = vec![];
+    while !chains.is_empty() {
+        let mut chain = chains.remove(0);
+        // once the last end cannot be extended, the chain is reversed to try its first end
+        let mut reversed = false;
+        loop {
+            let last = chain.nodes.last().unwrap();
+            let own_distance = distance(chain.nodes.first().unwrap(), last);
+            let nearest = chains
+                .iter()
+                .enumerate()
+                .flat_map(|(i, other)| {
+                    vec![
+                        (i, false, distance(last, other.nodes.first().unwrap())),
+                        (i, true, distance(last, other.nodes.last().unwrap())),
+                    ]
+                })
+                .filter(|&(_, _, d)| d <= tolerance && d < own_distance)
+                .min_by(|a, b| a.2.total_cmp(&b.2));
+
+            if let Some((i, reverse, distance)) = nearest {
+                let mut other = chains.remove(i);
+                if reverse {
+                    other.reverse();
+                }
+                issues.push(BoundaryIssue::RepairedGap {
+                    from: last.id,
+                    to: other.nodes[0].id,
+                    distance,
+                });
+                chain.ways.push(None);
+                chain.ways.extend(other.ways);
+                chain.nodes.extend(other.nodes);
+            } else if own_distance <= tolerance {
+                let (first, last) = (chain.nodes.first().unwrap().id, last.id);
+                issues.push(BoundaryIssue::RepairedGap {
+                    from: last,
+                    to: first,
+                    distance: own_distance,
+                });
+                if chain.nodes.len() >= options.min_ring_nodes {
+                    chain.ways.push(None);
+                    rings.push(Ring {
+                        nodes: chain.nodes,
+                        ways: chain.ways,
+                    });
+                } else {
+                    debug!(
+                        "Ignored repaired ring with less than {} nodes in relation:{} at node:{}",
+                        options.min_ring_nodes, relation.id.0, first.0
+                    );
+                    issues.push(BoundaryIssue::DegenerateRing { node: first });
+                }
+                break;
+            } else if !reversed {
+                chain.reverse();
+                reversed = true;
+            } else {
+                unclosed.push(chain);
+                break;
+            }
+        }
+    }
+    unclosed
+}
+
+#[test]
+fn test_build_boundary_empty() {
+    let objects: BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj> = BTreeMap::new();
+    let mut relation = osmpbfreader::Relation {
+        id: osmpbfreader::RelationId(12),
+        refs: vec![],
+        tags: osmpbfreader::Tags::new(),
+    };
+    relation.refs.push(osmpbfreader::Ref {
+        member: osmpbfreader::WayId(4).into(),
+        role: "outer".into(),
+    });
+    relation.refs.push(osmpbfreader::Ref {
+        member: osmpbfreader::WayId(65).into(),
         role: "outer".into(),
     });
     relation.refs.push(osmpbfreader::Ref {
@@ -328,7 +1889,7 @@ fn test_build_boundary_not_closed() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        assert!(build_boundary(&relation, &builder.objects).is_none());
+        assert!(build_boundary(relation, &builder.objects).is_none());
     } else {
         unreachable!()
     }
@@ -351,7 +1912,7 @@ fn test_build_boundary_closed() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 1);
@@ -377,7 +1938,7 @@ fn test_build_boundary_closed_reverse() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 1);
@@ -400,7 +1961,7 @@ fn test_build_one_boundary_closed() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 1);
@@ -432,19 +1993,72 @@ fn test_build_two_opposite_clockwise_boundaries() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 2);
         let centroid = multipolygon.centroid();
         let centroid = centroid.unwrap();
-        assert!(centroid.lng().abs() < f64::EPSILON);
-        assert!(centroid.lat().abs() < f64::EPSILON);
+        assert!(centroid.x().abs() < f64::EPSILON);
+        assert!(centroid.y().abs() < f64::EPSILON);
     } else {
         unreachable!()
     }
 }
 
+#[test]
+fn test_build_boundary_with_normalized_winding() {
+    use geo::winding_order::Winding;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"), // clockwise polygon
+            named_node(0.0, 4.0, "1"),
+            named_node(4.0, 4.0, "2"),
+            named_node(4.0, 0.0, "3"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .inner(vec![
+            named_node(1.0, 1.0, "inner_start"), // anti-clockwise inner
+            named_node(2.0, 1.0, "4"),
+            named_node(2.0, 2.0, "5"),
+            named_node(1.0, 1.0, "inner_start"),
+        ])
+        .outer(vec![
+            named_node(5.0, 5.0, "other_start"), // anti-clockwise polygon
+            named_node(6.0, 5.0, "6"),
+            named_node(6.0, 6.0, "7"),
+            named_node(5.0, 5.0, "other_start"),
+        ])
+        .relation_id;
+    let relation = builder.objects[&rel_id.into()].relation().unwrap();
+
+    let multipolygon = BoundaryBuilder::new()
+        .winding(RingWinding::CounterClockwise)
+        .build(relation, &builder.objects)
+        .boundary
+        .unwrap();
+    assert!(multipolygon.0.iter().all(|p| p.exterior().is_ccw()));
+    assert!(multipolygon.0[0].interiors()[0].is_cw());
+
+    let multipolygon = BoundaryBuilder::new()
+        .winding(RingWinding::Clockwise)
+        .build(relation, &builder.objects)
+        .boundary
+        .unwrap();
+    assert!(multipolygon.0.iter().all(|p| p.exterior().is_cw()));
+    assert!(multipolygon.0[0].interiors()[0].is_ccw());
+
+    let report = BoundaryBuilder::new()
+        .winding(RingWinding::Clockwise)
+        .validate(true)
+        .make_valid(true)
+        .build(relation, &builder.objects);
+    assert!(report.issues.is_empty());
+    assert_eq!(report.boundary.unwrap(), multipolygon);
+}
+
 #[test]
 fn test_build_two_boundaries_closed() {
     let mut builder = osm_builder::OsmBuilder::new();
@@ -465,7 +2079,7 @@ fn test_build_two_boundaries_closed() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 2);
@@ -497,7 +2111,7 @@ fn test_build_one_donut_boundary() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 1);
@@ -537,7 +2151,7 @@ fn test_build_two_boundaries_with_one_hole() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 2);
@@ -577,7 +2191,7 @@ fn test_build_one_boundary_with_two_holes() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 1);
@@ -625,7 +2239,7 @@ fn test_build_two_boundaries_with_two_holes() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 2);
@@ -664,7 +2278,7 @@ fn test_build_inner_touching_outer_at_one_point() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 1);
@@ -706,7 +2320,7 @@ fn test_build_two_touching_rings() {
         .relation_id
         .into();
     if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
-        let multipolygon = build_boundary(&relation, &builder.objects);
+        let multipolygon = build_boundary(relation, &builder.objects);
         assert!(multipolygon.is_some());
         let multipolygon = multipolygon.unwrap();
         assert_eq!(multipolygon.0.len(), 2);
@@ -715,3 +2329,1085 @@ fn test_build_two_touching_rings() {
         unreachable!()
     }
 }
+
+#[test]
+fn test_build_boundary_checked_missing_members() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder.relation().relation_id;
+    if let osmpbfreader::OsmObj::Relation(ref mut relation) =
+        builder.objects.get_mut(&rel_id.into()).unwrap()
+    {
+        relation.refs.push(osmpbfreader::Ref {
+            member: osmpbfreader::WayId(42).into(),
+            role: "outer".into(),
+        });
+    }
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id.into()] {
+        let report = build_boundary_checked(relation, &builder.objects);
+        assert_eq!(report.boundary, Err(BoundaryError::MissingMembers));
+        assert_eq!(
+            report.issues,
+            vec![BoundaryIssue::MissingWay(osmpbfreader::WayId(42))]
+        );
+    } else {
+        unreachable!()
+    }
+
+    let way_id = osmpbfreader::WayId(43);
+    builder.objects.insert(
+        way_id.into(),
+        osmpbfreader::Way {
+            id: way_id,
+            nodes: vec![osmpbfreader::NodeId(1), osmpbfreader::NodeId(2)],
+            tags: osmpbfreader::Tags::new(),
+        }
+        .into(),
+    );
+    if let osmpbfreader::OsmObj::Relation(ref mut relation) =
+        builder.objects.get_mut(&rel_id.into()).unwrap()
+    {
+        relation.refs[0].member = way_id.into();
+    }
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id.into()] {
+        let report = build_boundary_checked(relation, &builder.objects);
+        assert_eq!(report.boundary, Err(BoundaryError::MissingNodes));
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_checked_not_closed() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(3.4, 5.2, "start"),
+            named_node(5.4, 5.1, "1"),
+        ])
+        .outer(vec![named_node(5.4, 5.1, "1"), named_node(2.4, 3.1, "end")])
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let report = build_boundary_checked(relation, &builder.objects);
+        assert_eq!(report.boundary, Err(BoundaryError::UnclosedRing));
+        assert_eq!(report.issues.len(), 1);
+        match report.issues[0] {
+            BoundaryIssue::UnclosedRing {
+                first,
+                last,
+                distance,
+            } => {
+                assert_eq!(first, osmpbfreader::NodeId(0));
+                assert_eq!(last, osmpbfreader::NodeId(2));
+                assert!(distance > 200_000.);
+            }
+            ref issue => panic!("unexpected issue {:?}", issue),
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_checked_degenerate_ring() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(1.0, 1.0, "1"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let report = build_boundary_checked(relation, &builder.objects);
+        assert_eq!(report.boundary, Err(BoundaryError::DegenerateRings));
+        assert_eq!(
+            report.issues,
+            vec![BoundaryIssue::DegenerateRing {
+                node: osmpbfreader::NodeId(0)
+            }]
+        );
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_checked_unassigned_inner() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(1.0, 0.0, "1"),
+            named_node(1.0, 1.0, "2"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .inner(vec![
+            named_node(5.0, 5.0, "other_start"),
+            named_node(6.0, 5.0, "11"),
+            named_node(6.0, 6.0, "12"),
+            named_node(5.0, 5.0, "other_start"),
+        ])
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let report = build_boundary_checked(relation, &builder.objects);
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 1);
+        assert!(multipolygon.0[0].interiors().is_empty());
+        assert_eq!(
+            report.issues,
+            vec![BoundaryIssue::UnassignedInner {
+                node: osmpbfreader::NodeId(3)
+            }]
+        );
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_nested_donuts_boundary() {
+    use geo::algorithm::area::Area;
+    let square = |min: f64, max: f64, name: &'static str| {
+        vec![
+            named_node(min, min, name),
+            (Point::new(max, min), None),
+            (Point::new(max, max), None),
+            (Point::new(min, max), None),
+            named_node(min, min, name),
+        ]
+    };
+    let mut builder = osm_builder::OsmBuilder::new();
+
+    /*
+        A boundary with a lake, having an island, having a pond, having an
+        islet, having a puddle. Inners are given from the most nested one, so
+        that the first outer enclosing them is never the right one.
+    */
+    let rel_id = builder
+        .relation()
+        .outer(square(0.0, 10.0, "boundary"))
+        .outer(square(2.0, 8.0, "island"))
+        .outer(square(4.0, 6.0, "islet"))
+        .inner(square(4.5, 5.5, "puddle"))
+        .inner(square(3.0, 7.0, "pond"))
+        .inner(square(1.0, 9.0, "lake"))
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let multipolygon = build_boundary(relation, &builder.objects);
+        assert!(multipolygon.is_some());
+        let multipolygon = multipolygon.unwrap();
+        assert_eq!(multipolygon.0.len(), 3);
+        for (poly, hole_area) in multipolygon.0.iter().zip([64., 16., 1.].iter()) {
+            assert_eq!(poly.interiors().len(), 1);
+            let hole = Polygon::new(poly.interiors()[0].clone(), vec![]);
+            assert!((hole.unsigned_area() - hole_area).abs() < f64::EPSILON);
+        }
+        assert!((multipolygon.unsigned_area() - 59.).abs() < f64::EPSILON);
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_ignoring_wrong_inner_role() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "1"),
+            named_node(4.0, 4.0, "2"),
+            named_node(0.0, 4.0, "3"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .outer(vec![
+            named_node(1.0, 1.0, "other_start"),
+            named_node(2.0, 1.0, "11"),
+            named_node(2.0, 2.0, "12"),
+            named_node(1.0, 2.0, "13"),
+            named_node(1.0, 1.0, "other_start"),
+        ])
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let multipolygon = build_boundary(relation, &builder.objects).unwrap();
+        assert!((multipolygon.unsigned_area() - 17.).abs() < f64::EPSILON);
+
+        let report = BoundaryBuilder::new()
+            .ignore_roles(true)
+            .build(relation, &builder.objects);
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 1);
+        assert_eq!(multipolygon.0[0].interiors().len(), 1);
+        assert!((multipolygon.unsigned_area() - 15.).abs() < f64::EPSILON);
+        assert_eq!(
+            report.issues,
+            vec![BoundaryIssue::WrongRole {
+                way: osmpbfreader::WayId(1),
+                role: "outer".into(),
+                computed: RingRole::Inner,
+            }]
+        );
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_ignoring_mixed_roles_ring() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "1"),
+            named_node(4.0, 4.0, "2"),
+        ])
+        .inner(vec![
+            named_node(4.0, 4.0, "2"),
+            named_node(0.0, 4.0, "3"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        assert_eq!(
+            build_boundary_checked(relation, &builder.objects).boundary,
+            Err(BoundaryError::UnclosedRing)
+        );
+
+        let report = BoundaryBuilder::new()
+            .ignore_roles(true)
+            .build(relation, &builder.objects);
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 1);
+        assert!((multipolygon.unsigned_area() - 16.).abs() < f64::EPSILON);
+        assert_eq!(
+            report.issues,
+            vec![BoundaryIssue::WrongRole {
+                way: osmpbfreader::WayId(1),
+                role: "inner".into(),
+                computed: RingRole::Outer,
+            }]
+        );
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_repairing_unclosed_ring() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(0.01, 0.0, "1"),
+            named_node(0.01, 0.01, "2"),
+        ])
+        .outer(vec![
+            named_node(0.01, 0.01, "2"),
+            named_node(0.0, 0.01, "3"),
+            named_node(0.0, 0.00001, "almost_start"),
+        ])
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let report = build_boundary_checked(relation, &builder.objects);
+        assert_eq!(report.boundary, Err(BoundaryError::UnclosedRing));
+
+        let report = BoundaryBuilder::new()
+            .repair_gaps(5.)
+            .build(relation, &builder.objects);
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 1);
+        assert!((multipolygon.unsigned_area() - 1e-4).abs() < 1e-9);
+        assert_eq!(report.issues.len(), 1);
+        match report.issues[0] {
+            BoundaryIssue::RepairedGap { from, to, distance } => {
+                assert_eq!(from, osmpbfreader::NodeId(4));
+                assert_eq!(to, osmpbfreader::NodeId(0));
+                assert!(distance > 1. && distance < 1.2);
+            }
+            ref issue => panic!("unexpected issue {:?}", issue),
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_repairing_missing_way() {
+    let mut builder = osm_builder::OsmBuilder::new();
+
+    /*
+        Two halves of a square whose ends are a few meters apart, as if the
+        ways joining them were missing.
+    */
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "A"),
+            named_node(0.01, 0.0, "B"),
+            named_node(0.01, 0.005, "C"),
+        ])
+        .outer(vec![
+            named_node(0.00002, 0.01, "D"),
+            named_node(0.0, 0.005, "E"),
+            named_node(0.0, 0.00002, "F"),
+        ])
+        .outer(vec![
+            named_node(0.01, 0.00502, "G"),
+            named_node(0.01, 0.01, "H"),
+            named_node(0.0, 0.01, "I"),
+        ])
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let report = BoundaryBuilder::new()
+            .repair_gaps(1.)
+            .build(relation, &builder.objects);
+        assert_eq!(report.boundary, Err(BoundaryError::UnclosedRing));
+
+        let report = BoundaryBuilder::new()
+            .repair_gaps(5.)
+            .build(relation, &builder.objects);
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 1);
+        assert_eq!(multipolygon.0[0].exterior().0.len(), 10);
+        let gaps: Vec<_> = report
+            .issues
+            .iter()
+            .map(|issue| match *issue {
+                BoundaryIssue::RepairedGap { from, to, .. } => (from.0, to.0),
+                ref issue => panic!("unexpected issue {:?}", issue),
+            })
+            .collect();
+        assert_eq!(gaps, vec![(2, 6), (8, 3), (5, 0)]);
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_with_custom_roles() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "1"),
+            named_node(4.0, 4.0, "2"),
+            named_node(0.0, 4.0, "3"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .inner(vec![
+            named_node(1.0, 1.0, "other_start"),
+            named_node(2.0, 1.0, "11"),
+            named_node(2.0, 2.0, "12"),
+            named_node(1.0, 2.0, "13"),
+            named_node(1.0, 1.0, "other_start"),
+        ])
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let multipolygon = BoundaryBuilder::new()
+            .outer_roles(&["outer", "inner"])
+            .inner_roles(&[])
+            .build(relation, &builder.objects)
+            .boundary
+            .unwrap();
+        assert_eq!(multipolygon.0.len(), 2);
+        assert!((multipolygon.unsigned_area() - 17.).abs() < f64::EPSILON);
+
+        let report = BoundaryBuilder::new()
+            .outer_roles(&["inner"])
+            .build(relation, &builder.objects);
+        assert_eq!(report.boundary.unwrap().0.len(), 1);
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_nested_donuts_boundary_matching_first_outer() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "1"),
+            named_node(4.0, 4.0, "2"),
+            named_node(0.0, 4.0, "3"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .outer(vec![
+            named_node(1.0, 1.0, "island_start"),
+            named_node(3.0, 1.0, "11"),
+            named_node(3.0, 3.0, "12"),
+            named_node(1.0, 3.0, "13"),
+            named_node(1.0, 1.0, "island_start"),
+        ])
+        .inner(vec![
+            named_node(1.5, 1.5, "pond_start"),
+            named_node(2.5, 1.5, "21"),
+            named_node(2.5, 2.5, "22"),
+            named_node(1.5, 2.5, "23"),
+            named_node(1.5, 1.5, "pond_start"),
+        ])
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let multipolygon = build_boundary(relation, &builder.objects).unwrap();
+        assert_eq!(multipolygon.0[0].interiors().len(), 0);
+        assert_eq!(multipolygon.0[1].interiors().len(), 1);
+
+        let multipolygon = BoundaryBuilder::new()
+            .inner_matching(InnerMatching::FirstEnclosing)
+            .build(relation, &builder.objects)
+            .boundary
+            .unwrap();
+        assert_eq!(multipolygon.0[0].interiors().len(), 1);
+        assert_eq!(multipolygon.0[1].interiors().len(), 0);
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_in_members_order() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    // the way ids are not in the members order
+    let island = builder.way(vec![
+        named_node(1.0, 1.0, "island_start"),
+        named_node(3.0, 1.0, "11"),
+        named_node(3.0, 3.0, "12"),
+        named_node(1.0, 3.0, "13"),
+        named_node(1.0, 1.0, "island_start"),
+    ]);
+    let outer = builder.way(vec![
+        named_node(0.0, 0.0, "start"),
+        named_node(4.0, 0.0, "1"),
+        named_node(4.0, 4.0, "2"),
+        named_node(0.0, 4.0, "3"),
+        named_node(0.0, 0.0, "start"),
+    ]);
+    let rel_id = builder
+        .relation()
+        .member("outer", outer.into())
+        .member("outer", island.into())
+        .inner(vec![
+            named_node(1.5, 1.5, "pond_start"),
+            named_node(2.5, 1.5, "21"),
+            named_node(2.5, 2.5, "22"),
+            named_node(1.5, 2.5, "23"),
+            named_node(1.5, 1.5, "pond_start"),
+        ])
+        .relation_id;
+    let relation = builder.objects[&rel_id.into()].relation().unwrap();
+
+    let multipolygon = build_boundary(relation, &builder.objects).unwrap();
+    assert_eq!(multipolygon.0[0].exterior().0[0], Coord { x: 0., y: 0. });
+    assert_eq!(multipolygon.0[0].interiors().len(), 0);
+    assert_eq!(multipolygon.0[1].interiors().len(), 1);
+
+    let multipolygon = BoundaryBuilder::new()
+        .inner_matching(InnerMatching::FirstEnclosing)
+        .build(relation, &builder.objects)
+        .boundary
+        .unwrap();
+    assert_eq!(multipolygon.0[0].exterior().0[0], Coord { x: 0., y: 0. });
+    assert_eq!(multipolygon.0[0].interiors().len(), 1);
+    assert_eq!(multipolygon.0[1].interiors().len(), 0);
+}
+
+#[test]
+fn test_build_boundary_validating_bow_tie() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(2.0, 2.0, "1"),
+            named_node(2.0, 0.0, "2"),
+            named_node(0.0, 2.0, "3"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .outer(vec![
+            named_node(5.0, 5.0, "other_start"),
+            named_node(6.0, 5.0, "11"),
+            named_node(6.0, 6.0, "12"),
+            named_node(5.0, 5.0, "other_start"),
+        ])
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let report = build_boundary_checked(relation, &builder.objects);
+        assert!(report.issues.is_empty());
+
+        let report = BoundaryBuilder::new()
+            .validate(true)
+            .build(relation, &builder.objects);
+        assert_eq!(report.boundary.unwrap().0.len(), 2);
+        assert_eq!(
+            report.issues,
+            vec![BoundaryIssue::SelfIntersection {
+                node: osmpbfreader::NodeId(0)
+            }]
+        );
+
+        let report = BoundaryBuilder::new()
+            .make_valid(true)
+            .build(relation, &builder.objects);
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 3);
+        assert!((multipolygon.unsigned_area() - 2.5).abs() < 1e-7);
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_making_valid_wrong_orientation() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"), // clockwise polygon
+            named_node(0.0, 4.0, "1"),
+            named_node(4.0, 4.0, "2"),
+            named_node(4.0, 0.0, "3"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .relation_id;
+    let relation = builder.objects[&rel_id.into()].relation().unwrap();
+
+    let multipolygon = BoundaryBuilder::new()
+        .make_valid(true)
+        .build(relation, &builder.objects)
+        .boundary
+        .unwrap();
+    assert_eq!(
+        multipolygon.0[0].exterior(),
+        &LineString::from(vec![(0., 0.), (4., 0.), (4., 4.), (0., 4.), (0., 0.)])
+    );
+}
+
+#[test]
+fn test_build_boundary_with_sub_relations() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let sub_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "1"),
+            named_node(4.0, 4.0, "2"),
+        ])
+        .outer(vec![
+            named_node(4.0, 4.0, "2"),
+            named_node(0.0, 4.0, "3"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .relation_id;
+    let multipolygon_id = builder
+        .relation()
+        .outer(vec![
+            named_node(1.0, 1.0, "island_start"),
+            named_node(3.0, 1.0, "11"),
+            named_node(3.0, 3.0, "12"),
+            named_node(1.0, 3.0, "13"),
+            named_node(1.0, 1.0, "island_start"),
+        ])
+        .inner(vec![
+            named_node(1.5, 1.5, "pond_start"),
+            named_node(2.5, 1.5, "21"),
+            named_node(2.5, 2.5, "22"),
+            named_node(1.5, 2.5, "23"),
+            named_node(1.5, 1.5, "pond_start"),
+        ])
+        .relation_id;
+    let rel_id = builder
+        .relation()
+        .member("outer", sub_id.into())
+        .member("inner", multipolygon_id.into())
+        .relation_id
+        .into();
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id] {
+        let report = build_boundary_checked(relation, &builder.objects);
+        assert_eq!(report.boundary, Err(BoundaryError::NoWayMember));
+
+        let report = BoundaryBuilder::new()
+            .expand_subrelations(1)
+            .build(relation, &builder.objects);
+        assert!(report.issues.is_empty());
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 2);
+        assert!((multipolygon.unsigned_area() - 13.).abs() < f64::EPSILON);
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_with_subarea_sub_relations() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let region_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(2.0, 0.0, "1"),
+            named_node(2.0, 2.0, "2"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .relation_id;
+    let country_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "11"),
+            named_node(4.0, 4.0, "12"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .member("subarea", region_id.into())
+        .member("label", osmpbfreader::NodeId(1).into())
+        .relation_id;
+    let rel_id = builder
+        .relation()
+        .member("outer", country_id.into())
+        .relation_id;
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id.into()] {
+        let report = BoundaryBuilder::new()
+            .expand_subrelations(2)
+            .build(relation, &builder.objects);
+        assert!(report.issues.is_empty());
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 1);
+        assert_eq!(multipolygon.0[0].exterior().0.len(), 4);
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_with_sub_relations_cycle() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .member("outer", osmpbfreader::RelationId(1).into())
+        .relation_id;
+    let sub_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "1"),
+            named_node(4.0, 4.0, "2"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .member("outer", rel_id.into())
+        .member("", osmpbfreader::RelationId(2).into())
+        .relation_id;
+    builder
+        .relation()
+        .outer(vec![
+            named_node(5.0, 5.0, "other_start"),
+            named_node(6.0, 5.0, "11"),
+            named_node(6.0, 6.0, "12"),
+            named_node(5.0, 5.0, "other_start"),
+        ])
+        .member("outer", sub_id.into());
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id.into()] {
+        let report = BoundaryBuilder::new()
+            .expand_subrelations(1)
+            .build(relation, &builder.objects);
+        assert_eq!(report.boundary.unwrap().0.len(), 1);
+        assert_eq!(
+            report.issues,
+            vec![
+                BoundaryIssue::RelationCycle(rel_id),
+                BoundaryIssue::TooDeepRelation(osmpbfreader::RelationId(2))
+            ]
+        );
+
+        let report = BoundaryBuilder::new()
+            .expand_subrelations(5)
+            .build(relation, &builder.objects);
+        assert_eq!(report.boundary.unwrap().0.len(), 2);
+        assert_eq!(
+            report.issues,
+            vec![
+                BoundaryIssue::RelationCycle(rel_id),
+                BoundaryIssue::RelationCycle(sub_id)
+            ]
+        );
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_from_scrambled_ways() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![named_node(4.0, 4.0, "C"), named_node(0.0, 4.0, "D")])
+        .outer(vec![named_node(4.0, 0.0, "B"), named_node(0.0, 0.0, "A")])
+        .outer(vec![named_node(0.0, 4.0, "D"), named_node(0.0, 0.0, "A")])
+        .outer(vec![named_node(4.0, 4.0, "C"), named_node(4.0, 0.0, "B")])
+        .relation_id;
+    if let osmpbfreader::OsmObj::Relation(ref relation) = builder.objects[&rel_id.into()] {
+        let report = build_boundary_checked(relation, &builder.objects);
+        assert!(report.issues.is_empty());
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 1);
+        assert_eq!(multipolygon.0[0].exterior().0.len(), 5);
+        assert!((multipolygon.unsigned_area() - 16.).abs() < 1e-7);
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_build_boundary_with_ambiguous_junctions() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    // three ways between the same two nodes, the middle one being no edge
+    // of the boundary
+    let top = builder.way(vec![
+        named_node(0.0, 0.0, "X"),
+        named_node(2.0, 2.0, "top"),
+        named_node(4.0, 0.0, "Y"),
+    ]);
+    let bottom = builder.way(vec![
+        named_node(0.0, 0.0, "X"),
+        named_node(2.0, -2.0, "bottom"),
+        named_node(4.0, 0.0, "Y"),
+    ]);
+    let middle = builder.way(vec![
+        named_node(0.0, 0.0, "X"),
+        named_node(2.0, 1.0, "middle"),
+        named_node(4.0, 0.0, "Y"),
+    ]);
+    let first_id = builder
+        .relation()
+        .member("outer", top.into())
+        .member("outer", bottom.into())
+        .member("outer", middle.into())
+        .relation_id;
+    let second_id = builder
+        .relation()
+        .member("outer", middle.into())
+        .member("outer", bottom.into())
+        .member("outer", top.into())
+        .relation_id;
+    for rel_id in [first_id, second_id].iter() {
+        let relation = builder.objects[&(*rel_id).into()].relation().unwrap();
+        let report = build_boundary_checked(relation, &builder.objects);
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 1);
+        assert!((multipolygon.unsigned_area() - 8.).abs() < 1e-7);
+        assert_eq!(report.issues.len(), 1);
+    }
+}
+
+#[test]
+fn test_build_boundary_from_faces() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let square = builder.way(vec![
+        named_node(0.0, 0.0, "A"),
+        named_node(4.0, 0.0, "B"),
+        named_node(4.0, 4.0, "C"),
+        named_node(0.0, 4.0, "D"),
+        named_node(0.0, 0.0, "A"),
+    ]);
+    // overlaps the square from B to C, and goes on to a dead end
+    let overlapping = builder.way(vec![
+        named_node(4.0, 0.0, "B"),
+        named_node(4.0, 4.0, "C"),
+        named_node(6.0, 6.0, "dead end"),
+    ]);
+    // touches itself at E
+    let figure_eight = builder.way(vec![
+        named_node(10.0, 0.0, "E"),
+        named_node(12.0, 0.0, "F"),
+        named_node(12.0, 2.0, "G"),
+        named_node(10.0, 0.0, "E"),
+        named_node(8.0, 0.0, "H"),
+        named_node(8.0, -2.0, "I"),
+        named_node(10.0, 0.0, "E"),
+    ]);
+    let rel_id = builder
+        .relation()
+        .member("outer", square.into())
+        .member("outer", overlapping.into())
+        .member("outer", square.into())
+        .member("outer", figure_eight.into())
+        .relation_id;
+    let relation = builder.objects[&rel_id.into()].relation().unwrap();
+
+    let report = BoundaryBuilder::new()
+        .ring_assembly(RingAssembly::Faces)
+        .build(relation, &builder.objects);
+    let multipolygon = report.boundary.unwrap();
+    assert_eq!(multipolygon.0.len(), 3);
+    assert!((multipolygon.unsigned_area() - 20.).abs() < 1e-7);
+    assert_eq!(report.issues.len(), 1);
+    match report.issues[0] {
+        BoundaryIssue::UnclosedRing { first, last, .. } => {
+            let mut ends = [first, last];
+            ends.sort();
+            assert_eq!(ends, [osmpbfreader::NodeId(2), osmpbfreader::NodeId(4)]);
+        }
+        ref issue => panic!("unexpected issue {:?}", issue),
+    }
+}
+
+#[test]
+fn test_build_boundary_from_faces_with_ambiguous_junctions() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "X"),
+            named_node(2.0, 2.0, "top"),
+            named_node(4.0, 0.0, "Y"),
+        ])
+        .outer(vec![
+            named_node(0.0, 0.0, "X"),
+            named_node(2.0, -2.0, "bottom"),
+            named_node(4.0, 0.0, "Y"),
+        ])
+        .outer(vec![
+            named_node(4.0, 0.0, "Y"),
+            named_node(2.0, 1.0, "middle"),
+            named_node(0.0, 0.0, "X"),
+        ])
+        .relation_id;
+    let relation = builder.objects[&rel_id.into()].relation().unwrap();
+    let report = BoundaryBuilder::new()
+        .ring_assembly(RingAssembly::Faces)
+        .build(relation, &builder.objects);
+    assert!(report.issues.is_empty());
+    // the faces on each side of the middle way are merged
+    let multipolygon = report.boundary.unwrap();
+    assert_eq!(multipolygon.0.len(), 1);
+    assert_eq!(multipolygon.0[0].exterior().0.len(), 5);
+    assert!((multipolygon.unsigned_area() - 8.).abs() < 1e-7);
+}
+
+#[test]
+fn test_build_boundary_from_faces_with_crossing_ways() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "A"),
+            named_node(4.0, 0.0, "B"),
+            named_node(4.0, 4.0, "C"),
+            named_node(0.0, 4.0, "D"),
+            named_node(0.0, 0.0, "A"),
+        ])
+        // crosses the first square without sharing any node with it
+        .outer(vec![
+            named_node(2.0, 2.0, "E"),
+            named_node(6.0, 2.0, "F"),
+            named_node(6.0, 6.0, "G"),
+            named_node(2.0, 6.0, "H"),
+            named_node(2.0, 2.0, "E"),
+        ])
+        .relation_id;
+    let relation = builder.objects[&rel_id.into()].relation().unwrap();
+    let builder_options = BoundaryBuilder::new().ring_assembly(RingAssembly::Faces);
+
+    let report = builder_options.build(relation, &builder.objects);
+    assert_eq!(
+        report.issues,
+        vec![BoundaryIssue::CrossingWays {
+            way: osmpbfreader::WayId(0),
+            other: osmpbfreader::WayId(1)
+        }]
+    );
+    let multipolygon = report.boundary.unwrap();
+    assert_eq!(multipolygon.0.len(), 1);
+    assert!((multipolygon.unsigned_area() - 28.).abs() < 1e-7);
+
+    let rings = builder_options
+        .build_rings(relation, &builder.objects)
+        .boundary
+        .unwrap();
+    let mut crossings: Vec<_> = rings.polygons[0]
+        .exterior
+        .nodes
+        .iter()
+        .filter(|n| n.0 < 0)
+        .collect();
+    crossings.sort();
+    assert_eq!(
+        crossings,
+        vec![&osmpbfreader::NodeId(-2), &osmpbfreader::NodeId(-1)]
+    );
+}
+
+#[test]
+fn test_build_boundary_from_faces_with_touching_inner() {
+    use geo::algorithm::area::Area;
+    let mut builder = osm_builder::OsmBuilder::new();
+    // the inner ring goes through a point of a segment of the outer ring
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "1"),
+            named_node(4.0, 4.0, "2"),
+            named_node(0.0, 4.0, "3"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .inner(vec![
+            named_node(2.0, 2.0, "other_start"),
+            named_node(1.0, 1.0, "11"),
+            named_node(2.0, 0.0, "touching"),
+            named_node(3.0, 1.0, "13"),
+            named_node(2.0, 2.0, "other_start"),
+        ])
+        .relation_id;
+    let relation = builder.objects[&rel_id.into()].relation().unwrap();
+    for &ignore_roles in &[false, true] {
+        let report = BoundaryBuilder::new()
+            .ignore_roles(ignore_roles)
+            .ring_assembly(RingAssembly::Faces)
+            .build(relation, &builder.objects);
+        assert!(report.issues.is_empty());
+        let multipolygon = report.boundary.unwrap();
+        assert_eq!(multipolygon.0.len(), 1);
+        assert_eq!(multipolygon.0[0].interiors().len(), 1);
+        assert!((multipolygon.unsigned_area() - 14.).abs() < 1e-7);
+    }
+}
+
+#[test]
+fn test_build_boundary_rings() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(0.0, 4.0, "1"),
+            named_node(4.0, 4.0, "2"),
+        ])
+        .outer(vec![
+            named_node(4.0, 4.0, "2"),
+            named_node(4.0, 0.0, "3"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .inner(vec![
+            named_node(1.0, 1.0, "inner_start"),
+            named_node(2.0, 1.0, "4"),
+            named_node(2.0, 2.0, "5"),
+            named_node(1.0, 1.0, "inner_start"),
+        ])
+        .relation_id;
+    let relation = builder.objects[&rel_id.into()].relation().unwrap();
+    let node = osmpbfreader::NodeId;
+    let way = |id| Some(osmpbfreader::WayId(id));
+
+    let rings = BoundaryBuilder::new()
+        .build_rings(relation, &builder.objects)
+        .boundary
+        .unwrap();
+    assert_eq!(rings.polygons.len(), 1);
+    let exterior = &rings.polygons[0].exterior;
+    assert_eq!(
+        exterior.nodes,
+        vec![node(0), node(1), node(2), node(3), node(0)]
+    );
+    assert_eq!(exterior.ways, vec![way(0), way(0), way(1), way(1)]);
+    assert_eq!(exterior.coords.0[1], Coord { x: 0., y: 4. });
+    assert_eq!(rings.polygons[0].interiors[0].ways, vec![way(2); 3]);
+    assert_eq!(
+        rings.to_multipolygon(),
+        build_boundary(relation, &builder.objects).unwrap()
+    );
+
+    let rings = BoundaryBuilder::new()
+        .winding(RingWinding::CounterClockwise)
+        .build_rings(relation, &builder.objects)
+        .boundary
+        .unwrap();
+    let exterior = &rings.polygons[0].exterior;
+    assert_eq!(
+        exterior.nodes,
+        vec![node(0), node(3), node(2), node(1), node(0)]
+    );
+    assert_eq!(exterior.ways, vec![way(1), way(1), way(0), way(0)]);
+}
+
+#[test]
+fn test_build_boundary_crossing_antimeridian() {
+    use geo::{Area, BoundingRect};
+    let mut builder = osm_builder::OsmBuilder::new();
+    let rel_id = builder
+        .relation()
+        .outer(vec![
+            named_node(179.0, -16.0, "start"),
+            named_node(-179.0, -16.0, "1"),
+            named_node(-179.0, -17.0, "2"),
+            named_node(179.0, -17.0, "3"),
+            named_node(179.0, -16.0, "start"),
+        ])
+        .inner(vec![
+            named_node(179.5, -16.25, "inner_start"),
+            named_node(-179.5, -16.25, "4"),
+            named_node(-179.5, -16.75, "5"),
+            named_node(179.5, -16.75, "6"),
+            named_node(179.5, -16.25, "inner_start"),
+        ])
+        .relation_id;
+    let relation = builder.objects[&rel_id.into()].relation().unwrap();
+    let start = builder.objects.values().find_map(|o| o.node()).unwrap().id;
+
+    let report = BoundaryBuilder::new().build(relation, &builder.objects);
+    assert!(report
+        .issues
+        .contains(&BoundaryIssue::AntimeridianCrossing { node: start }));
+    let multipolygon = report.boundary.unwrap();
+    assert!(multipolygon.bounding_rect().unwrap().width() > 350.);
+
+    let multipolygon = BoundaryBuilder::new()
+        .antimeridian(Antimeridian::Shift)
+        .build(relation, &builder.objects)
+        .boundary
+        .unwrap();
+    let rect = multipolygon.bounding_rect().unwrap();
+    assert_eq!((rect.min().x, rect.max().x), (179., 181.));
+    assert_eq!(multipolygon.0[0].interiors().len(), 1);
+    assert!((multipolygon.unsigned_area() - 1.5).abs() < 1e-6);
+
+    let multipolygon = BoundaryBuilder::new()
+        .antimeridian(Antimeridian::Split)
+        .validate(true)
+        .build(relation, &builder.objects)
+        .boundary
+        .unwrap();
+    assert_eq!(multipolygon.0.len(), 2);
+    for polygon in &multipolygon.0 {
+        let rect = polygon.bounding_rect().unwrap();
+        assert!(rect.width() <= 1. + 1e-6);
+        assert!((polygon.unsigned_area() - 0.75).abs() < 1e-6);
+    }
+}
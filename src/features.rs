@@ -0,0 +1,122 @@
+extern crate osmpbfreader;
+
+use crate::boundaries::{BoundaryIssue, BoundaryReport};
+use geojson::{Feature, FeatureWriter, Geometry, JsonObject, JsonValue};
+use std::io::{self, Write};
+
+#[cfg(test)]
+use crate::boundaries::BoundaryBuilder;
+#[cfg(test)]
+use crate::osm_builder;
+#[cfg(test)]
+use crate::osm_builder::named_node;
+
+/// Converts a built boundary to a GeoJSON feature, with the id of the
+/// relation and its tags as properties.
+///
+/// The diagnostics are added to the properties as `@`-prefixed keys:
+/// `@repaired` if a gap has been bridged, `@invalid` if the geometry has
+/// been reported invalid, and `@error` (with no geometry) if the boundary
+/// could not be built.
+pub fn boundary_feature(relation: &osmpbfreader::Relation, report: &BoundaryReport) -> Feature {
+    let mut properties: JsonObject = relation
+        .tags
+        .iter()
+        .map(|(k, v)| (k.to_string(), JsonValue::from(v.as_str())))
+        .collect();
+    let repaired = report
+        .issues
+        .iter()
+        .any(|issue| matches!(issue, BoundaryIssue::RepairedGap { .. }));
+    let invalid = report.issues.iter().any(|issue| {
+        matches!(
+            issue,
+            BoundaryIssue::SelfIntersection { .. } | BoundaryIssue::InvalidGeometry(_)
+        )
+    });
+    properties.insert("@repaired".into(), repaired.into());
+    properties.insert("@invalid".into(), invalid.into());
+    if let Err(ref e) = report.boundary {
+        properties.insert("@error".into(), e.to_string().into());
+    }
+    Feature {
+        bbox: None,
+        geometry: report
+            .boundary
+            .as_ref()
+            .ok()
+            .map(|multipolygon| Geometry::new(multipolygon.into())),
+        id: Some(geojson::feature::Id::Number(relation.id.0.into())),
+        properties: Some(properties),
+        foreign_members: None,
+    }
+}
+
+/// Writes the boundaries as a GeoJSON `FeatureCollection`, one feature at
+/// a time, see `boundary_feature`.
+pub fn write_boundary_features<'a, W, I>(writer: W, boundaries: I) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = (&'a osmpbfreader::Relation, &'a BoundaryReport)>,
+{
+    let mut writer = FeatureWriter::from_writer(writer);
+    for (relation, report) in boundaries {
+        writer
+            .write_feature(&boundary_feature(relation, report))
+            .map_err(io::Error::other)?;
+    }
+    writer.finish().map_err(io::Error::other)
+}
+
+#[test]
+fn test_boundary_features() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let square = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "a"),
+            named_node(1.0, 0.0, "b"),
+            named_node(1.0, 1.0, "c"),
+            named_node(0.0, 1.0, "d"),
+            named_node(0.0, 0.0, "a"),
+        ])
+        .relation_id;
+    let empty = builder.relation().relation_id;
+    if let osmpbfreader::OsmObj::Relation(ref mut relation) =
+        builder.objects.get_mut(&square.into()).unwrap()
+    {
+        relation.tags.insert("name".into(), "square".into());
+    }
+    let relations: Vec<_> = [square, empty]
+        .iter()
+        .map(|id| builder.objects[&(*id).into()].relation().unwrap().clone())
+        .collect();
+    let reports: Vec<_> = relations
+        .iter()
+        .map(|r| BoundaryBuilder::new().build(r, &builder.objects))
+        .collect();
+
+    let feature = boundary_feature(&relations[0], &reports[0]);
+    assert_eq!(
+        feature.id,
+        Some(geojson::feature::Id::Number(square.0.into()))
+    );
+    assert_eq!(feature.property("name"), Some(&JsonValue::from("square")));
+    assert_eq!(feature.property("@repaired"), Some(&JsonValue::from(false)));
+    assert_eq!(feature.property("@invalid"), Some(&JsonValue::from(false)));
+    assert!(!feature.contains_property("@error"));
+    match feature.geometry.unwrap().value {
+        geojson::Value::MultiPolygon(ref polygons) => assert_eq!(polygons.len(), 1),
+        ref value => panic!("unexpected geometry {:?}", value),
+    }
+
+    let mut json = vec![];
+    write_boundary_features(&mut json, relations.iter().zip(&reports)).unwrap();
+    let collection = match String::from_utf8(json).unwrap().parse().unwrap() {
+        geojson::GeoJson::FeatureCollection(collection) => collection,
+        json => panic!("unexpected GeoJSON {:?}", json),
+    };
+    assert_eq!(collection.features.len(), 2);
+    assert!(collection.features[1].geometry.is_none());
+    assert!(collection.features[1].contains_property("@error"));
+}
@@ -0,0 +1,193 @@
+extern crate osmpbfreader;
+
+use crate::index::IndexedBoundary;
+use geo::{Area, BooleanOps, BoundingRect, Contains};
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::{Envelope, RTree, RTreeObject};
+use std::collections::BTreeMap;
+
+#[cfg(test)]
+use geo_types::MultiPolygon;
+
+/// Link from a boundary to a boundary containing it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HierarchyLink {
+    pub parent: osmpbfreader::RelationId,
+    /// Part of the area of the boundary inside its parent.
+    pub overlap: f64,
+}
+
+/// Administrative hierarchy of boundaries, each boundary being linked to
+/// the boundaries with a lower `admin_level` containing it.
+#[derive(Debug, Clone, Default)]
+pub struct Hierarchy {
+    // links of each boundary, the closest level first
+    ancestors: BTreeMap<osmpbfreader::RelationId, Vec<HierarchyLink>>,
+}
+
+impl Hierarchy {
+    /// Links each boundary to the boundaries with a lower `admin_level`
+    /// covering at least `min_overlap` of its area, to tolerate slight
+    /// mismatches between their borders.
+    ///
+    /// The areas are computed in squared degrees, only for the boundaries
+    /// not contained in their parent, the others being entirely covered.
+    /// The boundaries without an `admin_level` are left out.
+    pub fn new(boundaries: &[IndexedBoundary], min_overlap: f64) -> Self {
+        let rects = boundaries
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.admin_level.is_some())
+            .filter_map(|(i, b)| {
+                let rect = b.boundary.bounding_rect()?;
+                let rect = Rectangle::from_corners(rect.min().into(), rect.max().into());
+                Some(GeomWithData::new(rect, i))
+            })
+            .collect();
+        let tree: RTree<GeomWithData<Rectangle<[f64; 2]>, usize>> = RTree::bulk_load(rects);
+
+        let mut ancestors = BTreeMap::new();
+        for rect in tree.iter() {
+            let child = &boundaries[rect.data];
+            let area = child.boundary.unsigned_area();
+            if area == 0. {
+                continue;
+            }
+            let envelope = rect.envelope();
+            let mut links: Vec<_> = tree
+                .locate_in_envelope_intersecting(&envelope)
+                .filter(|candidate| boundaries[candidate.data].admin_level < child.admin_level)
+                .filter_map(|candidate| {
+                    let parent = &boundaries[candidate.data];
+                    // the intersection is only computed for the boundaries
+                    // straddling the border of their parent
+                    let inside = candidate.envelope().contains_envelope(&envelope)
+                        && parent.boundary.contains(&child.boundary);
+                    let overlap = if inside {
+                        1.
+                    } else {
+                        child
+                            .boundary
+                            .intersection(&parent.boundary)
+                            .unsigned_area()
+                            / area
+                    };
+                    if overlap >= min_overlap {
+                        Some((parent.admin_level, parent.id, overlap))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            links.sort_by(|a, b| b.0.cmp(&a.0).then(b.2.total_cmp(&a.2)).then(a.1.cmp(&b.1)));
+            let links = links
+                .into_iter()
+                .map(|(_, parent, overlap)| HierarchyLink { parent, overlap })
+                .collect();
+            ancestors.insert(child.id, links);
+        }
+        Hierarchy { ancestors }
+    }
+
+    /// Boundaries containing a boundary, the highest `admin_level` first.
+    pub fn ancestors(&self, id: osmpbfreader::RelationId) -> &[HierarchyLink] {
+        self.ancestors.get(&id).map_or(&[], |links| links)
+    }
+
+    /// Closest boundary containing a boundary, the one with the highest
+    /// `admin_level` (and the largest overlap between boundaries of the same
+    /// level).
+    pub fn parent(&self, id: osmpbfreader::RelationId) -> Option<&HierarchyLink> {
+        self.ancestors(id).first()
+    }
+
+    /// Boundaries whose parent is a boundary.
+    pub fn children(&self, id: osmpbfreader::RelationId) -> Vec<osmpbfreader::RelationId> {
+        self.ancestors
+            .iter()
+            .filter(|(_, links)| links.first().is_some_and(|link| link.parent == id))
+            .map(|(child, _)| *child)
+            .collect()
+    }
+
+    /// Boundaries without any parent.
+    pub fn roots(&self) -> Vec<osmpbfreader::RelationId> {
+        self.ancestors
+            .iter()
+            .filter(|(_, links)| links.is_empty())
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
+
+#[test]
+fn test_hierarchy() {
+    fn boundary(id: i64, admin_level: &str, min: (f64, f64), max: (f64, f64)) -> IndexedBoundary {
+        let mut tags = osmpbfreader::Tags::new();
+        tags.insert("admin_level".into(), admin_level.into());
+        let relation = osmpbfreader::Relation {
+            id: osmpbfreader::RelationId(id),
+            tags,
+            refs: vec![],
+        };
+        let rect = geo_types::Rect::new(min, max).to_polygon();
+        IndexedBoundary::new(&relation, MultiPolygon(vec![rect]))
+    }
+    let id = osmpbfreader::RelationId;
+    let hierarchy = Hierarchy::new(
+        &[
+            boundary(1, "4", (0., 0.), (10., 10.)),
+            boundary(2, "6", (0., 0.), (5., 5.)),
+            boundary(3, "8", (0., 0.), (2., 2.)),
+            // slightly crossing the border of its department
+            boundary(4, "8", (4., 0.), (5.1, 1.)),
+            // mostly outside of the department
+            boundary(5, "8", (4.9, 0.), (6., 1.)),
+            boundary(6, "4", (20., 0.), (30., 10.)),
+        ],
+        0.9,
+    );
+
+    assert_eq!(hierarchy.roots(), vec![id(1), id(6)]);
+    assert_eq!(hierarchy.children(id(1)), vec![id(2), id(5)]);
+    assert_eq!(hierarchy.children(id(2)), vec![id(3), id(4)]);
+    assert_eq!(hierarchy.parent(id(3)).unwrap().overlap, 1.);
+    let ancestors = hierarchy.ancestors(id(4));
+    assert_eq!(ancestors.len(), 2);
+    assert_eq!(ancestors[0].parent, id(2));
+    assert!((ancestors[0].overlap - 1. / 1.1).abs() < 1e-9);
+    assert_eq!(ancestors[1].parent, id(1));
+    assert_eq!(hierarchy.parent(id(5)).unwrap().parent, id(1));
+    assert!(hierarchy.ancestors(id(7)).is_empty());
+}
+
+#[test]
+fn test_hierarchy_with_enclave_in_parent() {
+    use geo_types::polygon;
+
+    let boundary = |id: i64, admin_level: &str, polygon: geo_types::Polygon<f64>| {
+        let mut tags = osmpbfreader::Tags::new();
+        tags.insert("admin_level".into(), admin_level.into());
+        let relation = osmpbfreader::Relation {
+            id: osmpbfreader::RelationId(id),
+            tags,
+            refs: vec![],
+        };
+        IndexedBoundary::new(&relation, MultiPolygon(vec![polygon]))
+    };
+    // the parent has an enclave inside its child, which has all its nodes
+    // in the parent but only 56% of its area
+    let parent = polygon!(
+        exterior: [(x: 0., y: 0.), (x: 10., y: 0.), (x: 10., y: 10.), (x: 0., y: 10.)],
+        interiors: [[(x: 3., y: 3.), (x: 3., y: 7.), (x: 7., y: 7.), (x: 7., y: 3.)]],
+    );
+    let child = polygon![(x: 2., y: 2.), (x: 8., y: 2.), (x: 8., y: 8.), (x: 2., y: 8.)];
+    let boundaries = [boundary(1, "4", parent), boundary(2, "6", child)];
+
+    let hierarchy = Hierarchy::new(&boundaries, 0.9);
+    assert!(hierarchy.parent(osmpbfreader::RelationId(2)).is_none());
+
+    let hierarchy = Hierarchy::new(&boundaries, 0.5);
+    let link = hierarchy.parent(osmpbfreader::RelationId(2)).unwrap();
+    assert!((link.overlap - 20. / 36.).abs() < 1e-9);
+}
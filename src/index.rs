@@ -0,0 +1,260 @@
+extern crate osmpbfreader;
+
+use geo::coordinate_position::CoordPos;
+use geo::kernels::{Kernel, Orientation, RobustKernel};
+use geo::BoundingRect;
+use geo_types::{Coord, MultiPolygon, Point, Polygon};
+use rstar::primitives::{GeomWithData, Line, Rectangle};
+use rstar::{Envelope, RTree, RTreeObject, AABB};
+use std::iter::FromIterator;
+
+/// `admin_level` tag of a relation, if it is a number.
+pub(crate) fn admin_level(relation: &osmpbfreader::Relation) -> Option<u32> {
+    relation
+        .tags
+        .get("admin_level")
+        .and_then(|level| level.parse().ok())
+}
+
+// bounding box of a polygon, with the index of its boundary and of the
+// polygon in the boundary
+type PolygonRect = GeomWithData<Rectangle<[f64; 2]>, (usize, usize)>;
+
+/// A boundary in a `BoundaryIndex`.
+#[derive(Debug, Clone)]
+pub struct IndexedBoundary {
+    pub id: osmpbfreader::RelationId,
+    pub tags: osmpbfreader::Tags,
+    pub admin_level: Option<u32>,
+    pub boundary: MultiPolygon<f64>,
+}
+
+impl IndexedBoundary {
+    pub fn new(relation: &osmpbfreader::Relation, boundary: MultiPolygon<f64>) -> Self {
+        IndexedBoundary {
+            id: relation.id,
+            tags: relation.tags.clone(),
+            admin_level: admin_level(relation),
+            boundary,
+        }
+    }
+}
+
+/// Index of boundaries, to find the ones containing a point.
+///
+/// The bounding boxes of the polygons of the boundaries are kept in an
+/// R-tree, so that only the polygons whose bounding box contains the point
+/// are tested. Each of these polygons is prepared with an R-tree of its
+/// segments, so that testing a point only looks at the segments crossed by
+/// a ray going from the point to the east edge of the bounding box.
+pub struct BoundaryIndex {
+    boundaries: Vec<IndexedBoundary>,
+    tree: RTree<PolygonRect>,
+    // segments of each polygon of each boundary
+    segments: Vec<Vec<RTree<Line<[f64; 2]>>>>,
+}
+
+/// Segments of the rings of a polygon.
+fn polygon_segments(polygon: &Polygon<f64>) -> RTree<Line<[f64; 2]>> {
+    RTree::bulk_load(
+        std::iter::once(polygon.exterior())
+            .chain(polygon.interiors())
+            .flat_map(|ring| ring.lines())
+            .map(|line| Line::new(line.start.into(), line.end.into()))
+            .collect(),
+    )
+}
+
+/// Position of a point relatively to a polygon given by its segments, by
+/// counting the segments crossed by a ray going east from the point up to
+/// `max_x`, the east edge of the bounding box of the polygon.
+fn locate(segments: &RTree<Line<[f64; 2]>>, max_x: f64, point: Point<f64>) -> CoordPos {
+    let p = Coord::from(point);
+    let ray = AABB::from_corners([p.x, p.y], [max_x.max(p.x), p.y]);
+    let mut crossings = 0;
+    for segment in segments.locate_in_envelope_intersecting(&ray) {
+        let (a, b) = (Coord::from(segment.from), Coord::from(segment.to));
+        let orientation = RobustKernel::orient2d(a, b, p);
+        if orientation == Orientation::Collinear && segment.envelope().contains_point(&[p.x, p.y]) {
+            return CoordPos::OnBoundary;
+        }
+        // a segment counts when it goes from below or on the ray to above
+        // it, or back, and passes east of the point
+        let crossing = match (a.y <= p.y, b.y <= p.y) {
+            (true, false) => orientation == Orientation::CounterClockwise,
+            (false, true) => orientation == Orientation::Clockwise,
+            _ => false,
+        };
+        if crossing {
+            crossings += 1;
+        }
+    }
+    if crossings % 2 == 1 {
+        CoordPos::Inside
+    } else {
+        CoordPos::Outside
+    }
+}
+
+impl FromIterator<IndexedBoundary> for BoundaryIndex {
+    fn from_iter<I: IntoIterator<Item = IndexedBoundary>>(iter: I) -> Self {
+        let boundaries: Vec<_> = iter.into_iter().collect();
+        let rects = boundaries
+            .iter()
+            .enumerate()
+            .flat_map(|(b, boundary)| {
+                boundary
+                    .boundary
+                    .0
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(p, polygon)| {
+                        let rect = polygon.bounding_rect()?;
+                        let rect = Rectangle::from_corners(rect.min().into(), rect.max().into());
+                        Some(GeomWithData::new(rect, (b, p)))
+                    })
+            })
+            .collect();
+        let segments = boundaries
+            .iter()
+            .map(|boundary| boundary.boundary.0.iter().map(polygon_segments).collect())
+            .collect();
+        BoundaryIndex {
+            boundaries,
+            tree: RTree::bulk_load(rects),
+            segments,
+        }
+    }
+}
+
+impl BoundaryIndex {
+    pub fn len(&self) -> usize {
+        self.boundaries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boundaries.is_empty()
+    }
+
+    /// Boundaries containing a point (or having it on their border), sorted
+    /// by `admin_level`, the ones without a level being last.
+    pub fn containing(&self, point: Point<f64>) -> Vec<&IndexedBoundary> {
+        self.find(point, |position| position != CoordPos::Outside)
+    }
+
+    /// Boundaries strictly containing a point, see `containing`.
+    pub fn strictly_containing(&self, point: Point<f64>) -> Vec<&IndexedBoundary> {
+        self.find(point, |position| position == CoordPos::Inside)
+    }
+
+    /// Boundaries having a polygon whose position relatively to the point
+    /// validates `accept`, sorted as in `containing`.
+    fn find<F>(&self, point: Point<f64>, accept: F) -> Vec<&IndexedBoundary>
+    where
+        F: Fn(CoordPos) -> bool,
+    {
+        let envelope = AABB::from_point([point.x(), point.y()]);
+        let mut found: Vec<usize> = self
+            .tree
+            .locate_in_envelope_intersecting(&envelope)
+            .filter(|rect| {
+                let (b, p) = rect.data;
+                let max_x = rect.geom().upper()[0];
+                accept(locate(&self.segments[b][p], max_x, point))
+            })
+            .map(|rect| rect.data.0)
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        let mut found: Vec<_> = found.into_iter().map(|b| &self.boundaries[b]).collect();
+        found.sort_by_key(|b| (b.admin_level.is_none(), b.admin_level, b.id));
+        found
+    }
+}
+
+#[test]
+fn test_boundary_index() {
+    fn boundary(id: i64, admin_level: Option<&str>, min: f64, max: f64) -> IndexedBoundary {
+        let mut tags = osmpbfreader::Tags::new();
+        if let Some(level) = admin_level {
+            tags.insert("admin_level".into(), level.into());
+        }
+        let relation = osmpbfreader::Relation {
+            id: osmpbfreader::RelationId(id),
+            tags,
+            refs: vec![],
+        };
+        let square = geo_types::Rect::new((min, min), (max, max)).to_polygon();
+        IndexedBoundary::new(&relation, MultiPolygon(vec![square]))
+    }
+    let index: BoundaryIndex = vec![
+        boundary(1, Some("8"), 0., 1.),
+        boundary(2, None, 0., 10.),
+        boundary(3, Some("2"), 0., 10.),
+        boundary(4, Some("8"), 1., 2.),
+        boundary(5, Some("4"), 0., 5.),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(index.len(), 5);
+
+    let ids = |found: Vec<&IndexedBoundary>| found.iter().map(|b| b.id.0).collect::<Vec<_>>();
+    assert_eq!(
+        ids(index.containing(Point::new(0.5, 0.5))),
+        vec![3, 5, 1, 2]
+    );
+    assert_eq!(
+        ids(index.containing(Point::new(1., 1.))),
+        vec![3, 5, 1, 4, 2]
+    );
+    assert_eq!(
+        ids(index.strictly_containing(Point::new(1., 1.))),
+        vec![3, 5, 2]
+    );
+    assert_eq!(ids(index.containing(Point::new(7., 7.))), vec![3, 2]);
+    assert!(index.containing(Point::new(11., 7.)).is_empty());
+}
+
+#[test]
+fn test_boundary_index_with_holes() {
+    use geo::{Contains, Intersects};
+    use geo_types::polygon;
+
+    let relation = |id: i64| osmpbfreader::Relation {
+        id: osmpbfreader::RelationId(id),
+        tags: osmpbfreader::Tags::new(),
+        refs: vec![],
+    };
+    // a concave polygon with a hole, and a vertex at the height of the
+    // points tested
+    let polygon = polygon!(
+        exterior: [
+            (x: 0., y: 0.), (x: 10., y: 0.), (x: 10., y: 10.), (x: 5., y: 5.),
+            (x: 0., y: 10.)
+        ],
+        interiors: [[(x: 2., y: 1.), (x: 2., y: 3.), (x: 4., y: 3.), (x: 4., y: 1.)]],
+    );
+    let index: BoundaryIndex = vec![IndexedBoundary::new(
+        &relation(1),
+        MultiPolygon(vec![polygon.clone()]),
+    )]
+    .into_iter()
+    .collect();
+    for x in 0..=24 {
+        for y in 0..=24 {
+            let point = Point::new(x as f64 / 2., y as f64 / 2.);
+            assert_eq!(
+                index.containing(point).len(),
+                polygon.intersects(&point) as usize,
+                "{:?}",
+                point
+            );
+            assert_eq!(
+                index.strictly_containing(point).len(),
+                polygon.contains(&point) as usize,
+                "{:?}",
+                point
+            );
+        }
+    }
+}
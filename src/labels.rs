@@ -0,0 +1,157 @@
+extern crate osmpbfreader;
+
+use crate::polylabel::pole_of_inaccessibility;
+use crate::store::NodeLocationStore;
+use geo::Intersects;
+use geo_types::{MultiPolygon, Point};
+
+#[cfg(test)]
+use crate::boundaries::BoundaryBuilder;
+#[cfg(test)]
+use crate::osm_builder;
+#[cfg(test)]
+use crate::osm_builder::named_node;
+
+/// Where a point of a boundary comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointSource {
+    /// A node member of the relation.
+    Member(osmpbfreader::NodeId),
+    /// Computed from the boundary, no node member being usable.
+    Computed,
+}
+
+/// A point of a boundary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundaryPoint {
+    pub point: Point<f64>,
+    pub source: PointSource,
+    /// The point lies outside of the boundary (only possible for a member).
+    pub outside: bool,
+}
+
+/// Display points of a boundary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundaryPoints {
+    /// The `label` member of the relation.
+    pub label: BoundaryPoint,
+    /// The `admin_centre` member of the relation.
+    pub admin_centre: BoundaryPoint,
+}
+
+/// Precision, in degrees, of the points computed by `boundary_points`.
+const COMPUTED_POINT_PRECISION: f64 = 1e-4;
+
+fn member_point<S: NodeLocationStore + ?Sized>(
+    relation: &osmpbfreader::Relation,
+    role: &str,
+    multipolygon: &MultiPolygon<f64>,
+    nodes: &S,
+) -> Option<BoundaryPoint> {
+    relation
+        .refs
+        .iter()
+        .filter(|r| r.role == role)
+        .filter_map(|r| r.member.node())
+        .find_map(|id| {
+            let point = Point::from(nodes.node_location(id)?);
+            Some(BoundaryPoint {
+                point,
+                source: PointSource::Member(id),
+                outside: !multipolygon.intersects(&point),
+            })
+        })
+}
+
+/// Finds the `label` and `admin_centre` points of a boundary built from a
+/// relation, the pole of inaccessibility of the boundary being used for
+/// the missing ones. Only the locations of the node members are read from
+/// `nodes`.
+///
+/// Returns `None` if a point is missing and none can be computed, the
+/// boundary being empty.
+pub fn boundary_points<S: NodeLocationStore + ?Sized>(
+    relation: &osmpbfreader::Relation,
+    multipolygon: &MultiPolygon<f64>,
+    nodes: &S,
+) -> Option<BoundaryPoints> {
+    let label = member_point(relation, "label", multipolygon, nodes);
+    let admin_centre = member_point(relation, "admin_centre", multipolygon, nodes);
+    // computed once, for both points if needed
+    let computed = match (label, admin_centre) {
+        (Some(_), Some(_)) => None,
+        _ => Some(BoundaryPoint {
+            point: pole_of_inaccessibility(multipolygon, COMPUTED_POINT_PRECISION)?,
+            source: PointSource::Computed,
+            outside: false,
+        }),
+    };
+    Some(BoundaryPoints {
+        label: label.or(computed)?,
+        admin_centre: admin_centre.or(computed)?,
+    })
+}
+
+#[test]
+fn test_boundary_points() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let label = builder.node(Point::new(1., 1.), None);
+    let town_hall = builder.node(Point::new(5., 5.), None);
+    let id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "a"),
+            named_node(2.0, 0.0, "b"),
+            named_node(2.0, 2.0, "c"),
+            named_node(0.0, 2.0, "d"),
+            named_node(0.0, 0.0, "a"),
+        ])
+        .relation_id;
+    let mut relation = builder.objects[&id.into()].relation().unwrap().clone();
+    let multipolygon = BoundaryBuilder::new()
+        .build(&relation, &builder.objects)
+        .boundary
+        .unwrap();
+
+    let points = boundary_points(&relation, &multipolygon, &builder.objects).unwrap();
+    assert_eq!(points.label.source, PointSource::Computed);
+    assert!(multipolygon.intersects(&points.label.point));
+    assert_eq!(points.admin_centre, points.label);
+
+    relation.refs.push(osmpbfreader::Ref {
+        member: label.into(),
+        role: "label".into(),
+    });
+    relation.refs.push(osmpbfreader::Ref {
+        member: town_hall.into(),
+        role: "admin_centre".into(),
+    });
+    let points = boundary_points(&relation, &multipolygon, &builder.objects).unwrap();
+    assert_eq!(
+        points.label,
+        BoundaryPoint {
+            point: Point::new(1., 1.),
+            source: PointSource::Member(label),
+            outside: false,
+        }
+    );
+    assert_eq!(points.admin_centre.source, PointSource::Member(town_hall));
+    assert!(points.admin_centre.outside);
+
+    // the node locations are enough
+    let nodes: crate::SortedNodeLocations = builder
+        .objects
+        .values()
+        .filter_map(|obj| obj.node())
+        .cloned()
+        .collect();
+    assert_eq!(
+        boundary_points(&relation, &multipolygon, &nodes),
+        Some(points)
+    );
+
+    assert!(boundary_points(&relation, &MultiPolygon(vec![]), &builder.objects).is_some());
+    let empty = builder.relation().relation_id;
+    let empty = builder.objects[&empty.into()].relation().unwrap().clone();
+    assert!(boundary_points(&empty, &MultiPolygon(vec![]), &builder.objects).is_none());
+}
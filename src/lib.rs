@@ -1,9 +1,55 @@
 extern crate geo;
 extern crate geo_types;
+#[cfg(feature = "geojson")]
+extern crate geojson;
 extern crate log;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
 extern crate osmpbfreader;
+#[cfg(test)]
+extern crate protobuf;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+extern crate rstar;
+#[cfg(feature = "serde_json")]
+extern crate serde_json;
 
+mod batch;
 mod boundaries;
+#[cfg(feature = "geojson")]
+mod features;
+mod hierarchy;
+mod index;
+mod labels;
 pub mod osm_builder;
+mod pbf;
+mod polylabel;
+#[cfg(feature = "postgis")]
+mod postgis;
+mod store;
+#[cfg(feature = "topojson")]
+mod topojson;
+mod topology;
+mod validation;
 
-pub use crate::boundaries::build_boundary;
+pub use crate::boundaries::{
+    build_boundary, build_boundary_checked, Antimeridian, BoundaryBuilder, BoundaryError,
+    BoundaryIssue, BoundaryOptions, BoundaryReport, BoundaryRings, InnerMatching, RingAssembly,
+    RingRole, RingWinding, TracedPolygon, TracedRing,
+};
+#[cfg(feature = "geojson")]
+pub use crate::features::{boundary_feature, write_boundary_features};
+pub use crate::hierarchy::{Hierarchy, HierarchyLink};
+pub use crate::index::{BoundaryIndex, IndexedBoundary};
+pub use crate::labels::{boundary_points, BoundaryPoint, BoundaryPoints, PointSource};
+pub use crate::pbf::{boundaries_from_pbf, is_admin_boundary, PbfBoundaries};
+pub use crate::polylabel::pole_of_inaccessibility;
+#[cfg(feature = "postgis")]
+pub use crate::postgis::{to_ewkb, to_ewkt, to_wkb, to_wkt, write_copy_rows, CopyTags, WGS84_SRID};
+pub use crate::store::{
+    DenseNodeLocations, NodeLocationStore, OsmObjectStore, SortedNodeLocations, SplitStore,
+};
+#[cfg(feature = "topojson")]
+pub use crate::topojson::{to_topojson, write_topojson};
+pub use crate::topology::{ArcPolygon, ArcRef, ArcRing, Simplification, Topology, TopologyArc};
+pub use crate::validation::{make_valid, validate_multipolygon, ValidityIssue};
@@ -12,7 +12,7 @@ pub struct Relation<'a> {
 }
 
 impl<'a> Relation<'a> {
-    pub fn outer(&mut self, coords: Vec<(Point<f64>, Option<String>)>) -> &'a mut Relation {
+    pub fn outer(&mut self, coords: Vec<(Point<f64>, Option<String>)>) -> &'a mut Relation<'_> {
         let id = self.builder.way(coords);
         if let osmpbfreader::OsmObj::Relation(ref mut rel) = self
             .builder
@@ -30,7 +30,7 @@ impl<'a> Relation<'a> {
 }
 
 impl<'a> Relation<'a> {
-    pub fn inner(&mut self, coords: Vec<(Point<f64>, Option<String>)>) -> &'a mut Relation {
+    pub fn inner(&mut self, coords: Vec<(Point<f64>, Option<String>)>) -> &'a mut Relation<'_> {
         let id = self.builder.way(coords);
         if let osmpbfreader::OsmObj::Relation(ref mut rel) = self
             .builder
@@ -47,6 +47,23 @@ impl<'a> Relation<'a> {
     }
 }
 
+impl<'a> Relation<'a> {
+    pub fn member(&mut self, role: &str, member: osmpbfreader::OsmId) -> &'a mut Relation<'_> {
+        if let osmpbfreader::OsmObj::Relation(ref mut rel) = self
+            .builder
+            .objects
+            .get_mut(&self.relation_id.into())
+            .unwrap()
+        {
+            rel.refs.push(osmpbfreader::Ref {
+                role: role.into(),
+                member,
+            });
+        }
+        self
+    }
+}
+
 #[derive(Default)]
 pub struct OsmBuilder {
     node_id: i64,
@@ -61,7 +78,7 @@ impl OsmBuilder {
         Self::default()
     }
 
-    pub fn relation(&mut self) -> Relation {
+    pub fn relation(&mut self) -> Relation<'_> {
         let id = osmpbfreader::RelationId(self.relation_id);
         let r = osmpbfreader::Relation {
             id,
@@ -0,0 +1,454 @@
+extern crate osmpbfreader;
+
+use crate::boundaries::{BoundaryBuilder, BoundaryOptions, BoundaryReport};
+use crate::index::admin_level;
+use crate::store::{OsmObjectStore, SortedNodeLocations, SplitStore};
+use geo_types::MultiPolygon;
+use log::info;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Seek};
+
+#[cfg(test)]
+use crate::osm_builder;
+#[cfg(test)]
+use crate::osm_builder::named_node;
+
+/// Iterator over the relations read by `BoundaryBuilder::build_from_pbf`,
+/// along with their boundary.
+pub struct PbfBoundaries {
+    builder: BoundaryBuilder,
+    relations: std::vec::IntoIter<osmpbfreader::RelationId>,
+    store: SplitStore<BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj>, SortedNodeLocations>,
+}
+
+impl Iterator for PbfBoundaries {
+    type Item = (osmpbfreader::Relation, BoundaryReport);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.relations.next()?;
+        let relation = self.store.objects[&id.into()].relation()?.clone();
+        let report = self.builder.build(&relation, &self.store);
+        Some((relation, report))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.relations.size_hint()
+    }
+}
+
+impl PbfBoundaries {
+    /// Objects read from the file, to be given to `boundary_points`.
+    pub fn store(&self) -> &impl OsmObjectStore {
+        &self.store
+    }
+}
+
+impl BoundaryBuilder {
+    /// Reads the relations validating `filter` from a pbf file, and builds
+    /// their boundaries.
+    ///
+    /// The file is read once for the relations (plus once per level of
+    /// sub-relations to expand), once for their ways and once for the nodes
+    /// of these ways and the node members of the relations, so that only the
+    /// objects needed by the boundaries are kept in memory, without their
+    /// tags except for the relations, and with only the location of the
+    /// nodes. The way and relation members are only read for the outer and
+    /// inner roles.
+    pub fn build_from_pbf<R, F>(
+        &self,
+        reader: &mut osmpbfreader::OsmPbfReader<R>,
+        filter: F,
+    ) -> osmpbfreader::Result<PbfBoundaries>
+    where
+        R: Read + Seek,
+        F: Fn(&osmpbfreader::Relation) -> bool,
+    {
+        let mut objects = BTreeMap::new();
+
+        reader.rewind()?;
+        let mut relations = vec![];
+        for obj in reader.par_iter() {
+            if let osmpbfreader::OsmObj::Relation(relation) = obj? {
+                if filter(&relation) {
+                    relations.push(relation.id);
+                    objects.insert(relation.id.into(), relation.into());
+                }
+            }
+        }
+        info!("{} relations read from pbf", relations.len());
+
+        let options = self.options();
+        let mut sub_relations = member_ids(&objects, options, |id| id.relation());
+        for _ in 0..options.max_subrelation_depth {
+            if sub_relations.is_empty() {
+                break;
+            }
+            reader.rewind()?;
+            for obj in reader.par_iter() {
+                if let osmpbfreader::OsmObj::Relation(relation) = obj? {
+                    if sub_relations.contains(&relation.id) {
+                        objects.insert(relation.id.into(), relation.into());
+                    }
+                }
+            }
+            sub_relations = member_ids(&objects, options, |id| id.relation());
+        }
+
+        let ways = member_ids(&objects, options, |id| id.way());
+        reader.rewind()?;
+        for obj in reader.par_iter() {
+            if let osmpbfreader::OsmObj::Way(mut way) = obj? {
+                if ways.contains(&way.id) {
+                    way.tags = osmpbfreader::Tags::new();
+                    objects.insert(way.id.into(), way.into());
+                }
+            }
+        }
+        info!("{} ways read from pbf", ways.len());
+
+        // the node members (e.g. `label`) are kept for `boundary_points`
+        let mut nodes: BTreeSet<_> = relations
+            .iter()
+            .filter_map(|id| objects[&(*id).into()].relation())
+            .flat_map(|relation| relation.refs.iter())
+            .filter_map(|r| r.member.node())
+            .collect();
+        nodes.extend(
+            objects
+                .values()
+                .filter_map(|obj| obj.way())
+                .flat_map(|way| way.nodes.iter().cloned()),
+        );
+        reader.rewind()?;
+        // collected straight into locations, the nodes being dropped one by one
+        let node_locations: SortedNodeLocations = reader
+            .par_iter()
+            .filter_map(|obj| match obj {
+                Ok(osmpbfreader::OsmObj::Node(node)) if nodes.contains(&node.id) => Some(Ok(node)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<osmpbfreader::Result<_>>()?;
+        info!("{} nodes read from pbf", node_locations.len());
+
+        Ok(PbfBoundaries {
+            builder: self.clone(),
+            relations: relations.into_iter(),
+            store: SplitStore {
+                objects,
+                nodes: node_locations,
+            },
+        })
+    }
+}
+
+/// Ids of the members of the relations with an outer or inner role that are
+/// not already in `objects`, the other members (e.g. `subarea`) not being
+/// used by the boundaries.
+fn member_ids<F, T>(
+    objects: &BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj>,
+    options: &BoundaryOptions,
+    id: F,
+) -> BTreeSet<T>
+where
+    F: Fn(&osmpbfreader::OsmId) -> Option<T>,
+    T: Ord,
+{
+    let is_boundary_role = |role: &str| {
+        options
+            .outer_roles
+            .iter()
+            .chain(options.inner_roles.iter())
+            .any(|r| r == role)
+    };
+    objects
+        .values()
+        .filter_map(|obj| obj.relation())
+        .flat_map(|relation| relation.refs.iter())
+        .filter(|r| is_boundary_role(&r.role) && !objects.contains_key(&r.member))
+        .filter_map(|r| id(&r.member))
+        .collect()
+}
+
+/// Reads the relations validating `filter` from a pbf file, and builds
+/// their boundaries, skipping those that cannot be built.
+///
+/// ```no_run
+/// let file = std::fs::File::open("france.osm.pbf").unwrap();
+/// let mut reader = osmpbfreader::OsmPbfReader::new(file);
+/// let boundaries = osm_boundaries_utils::boundaries_from_pbf(
+///     &mut reader,
+///     osm_boundaries_utils::is_admin_boundary(8),
+/// )
+/// .unwrap();
+/// for (relation, multipolygon) in boundaries {
+///     println!("relation {}: {} polygons", relation.id.0, multipolygon.0.len());
+/// }
+/// ```
+pub fn boundaries_from_pbf<R, F>(
+    reader: &mut osmpbfreader::OsmPbfReader<R>,
+    filter: F,
+) -> osmpbfreader::Result<impl Iterator<Item = (osmpbfreader::Relation, MultiPolygon<f64>)>>
+where
+    R: Read + Seek,
+    F: Fn(&osmpbfreader::Relation) -> bool,
+{
+    Ok(BoundaryBuilder::new()
+        .build_from_pbf(reader, filter)?
+        .filter_map(|(relation, report)| report.boundary.ok().map(|b| (relation, b))))
+}
+
+/// Filter for `boundaries_from_pbf` keeping the administrative boundaries
+/// with an `admin_level` up to `max_admin_level`.
+pub fn is_admin_boundary(max_admin_level: u32) -> impl Fn(&osmpbfreader::Relation) -> bool {
+    move |relation| {
+        relation.tags.contains("boundary", "administrative")
+            && admin_level(relation).is_some_and(|level| level <= max_admin_level)
+    }
+}
+
+/// Writes the objects as an uncompressed pbf file, with one block per kind
+/// of object.
+#[cfg(test)]
+pub(crate) fn write_pbf(objects: &BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj>) -> Vec<u8> {
+    use osmpbfreader::{fileformat, osmformat};
+    use protobuf::{Message, RepeatedField};
+
+    fn string_id(strings: &mut Vec<String>, s: &str) -> u32 {
+        match strings.iter().position(|known| known == s) {
+            Some(idx) => idx as u32,
+            None => {
+                strings.push(s.to_string());
+                (strings.len() - 1) as u32
+            }
+        }
+    }
+    fn tags(strings: &mut Vec<String>, tags: &osmpbfreader::Tags) -> (Vec<u32>, Vec<u32>) {
+        tags.iter()
+            .map(|(k, v)| (string_id(strings, k), string_id(strings, v)))
+            .unzip()
+    }
+
+    let mut strings = vec![String::new()];
+    let delta = |ids: Vec<i64>| {
+        let mut previous = 0;
+        ids.into_iter()
+            .map(|id| {
+                let delta = id - previous;
+                previous = id;
+                delta
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut nodes = vec![];
+    let mut ways = vec![];
+    let mut relations = vec![];
+    for obj in objects.values() {
+        match *obj {
+            osmpbfreader::OsmObj::Node(ref node) => {
+                let mut pbf_node = osmformat::Node::new();
+                pbf_node.set_id(node.id.0);
+                pbf_node.set_lat(node.decimicro_lat as i64);
+                pbf_node.set_lon(node.decimicro_lon as i64);
+                let (keys, vals) = tags(&mut strings, &node.tags);
+                pbf_node.set_keys(keys);
+                pbf_node.set_vals(vals);
+                nodes.push(pbf_node);
+            }
+            osmpbfreader::OsmObj::Way(ref way) => {
+                let mut pbf_way = osmformat::Way::new();
+                pbf_way.set_id(way.id.0);
+                pbf_way.set_refs(delta(way.nodes.iter().map(|n| n.0).collect()));
+                let (keys, vals) = tags(&mut strings, &way.tags);
+                pbf_way.set_keys(keys);
+                pbf_way.set_vals(vals);
+                ways.push(pbf_way);
+            }
+            osmpbfreader::OsmObj::Relation(ref relation) => {
+                use osmpbfreader::osmformat::Relation_MemberType as MemberType;
+                let mut pbf_relation = osmformat::Relation::new();
+                pbf_relation.set_id(relation.id.0);
+                pbf_relation.set_roles_sid(
+                    relation
+                        .refs
+                        .iter()
+                        .map(|r| string_id(&mut strings, &r.role) as i32)
+                        .collect(),
+                );
+                pbf_relation.set_memids(delta(
+                    relation.refs.iter().map(|r| r.member.inner_id()).collect(),
+                ));
+                pbf_relation.set_types(
+                    relation
+                        .refs
+                        .iter()
+                        .map(|r| match r.member {
+                            osmpbfreader::OsmId::Node(_) => MemberType::NODE,
+                            osmpbfreader::OsmId::Way(_) => MemberType::WAY,
+                            osmpbfreader::OsmId::Relation(_) => MemberType::RELATION,
+                        })
+                        .collect(),
+                );
+                let (keys, vals) = tags(&mut strings, &relation.tags);
+                pbf_relation.set_keys(keys);
+                pbf_relation.set_vals(vals);
+                relations.push(pbf_relation);
+            }
+        }
+    }
+
+    let mut node_group = osmformat::PrimitiveGroup::new();
+    node_group.set_nodes(RepeatedField::from_vec(nodes));
+    let mut way_group = osmformat::PrimitiveGroup::new();
+    way_group.set_ways(RepeatedField::from_vec(ways));
+    let mut relation_group = osmformat::PrimitiveGroup::new();
+    relation_group.set_relations(RepeatedField::from_vec(relations));
+
+    let mut string_table = osmformat::StringTable::new();
+    string_table.set_s(RepeatedField::from_vec(
+        strings.into_iter().map(String::into_bytes).collect(),
+    ));
+    let mut block = osmformat::PrimitiveBlock::new();
+    block.set_stringtable(string_table);
+    block.set_primitivegroup(RepeatedField::from_vec(vec![
+        node_group,
+        way_group,
+        relation_group,
+    ]));
+
+    let data = block.write_to_bytes().unwrap();
+    let mut blob = fileformat::Blob::new();
+    blob.set_raw_size(data.len() as i32);
+    blob.set_raw(data);
+    let blob = blob.write_to_bytes().unwrap();
+    let mut header = fileformat::BlobHeader::new();
+    header.set_field_type("OSMData".into());
+    header.set_datasize(blob.len() as i32);
+    let header = header.write_to_bytes().unwrap();
+
+    let mut pbf = (header.len() as u32).to_be_bytes().to_vec();
+    pbf.extend(header);
+    pbf.extend(blob);
+    pbf
+}
+
+#[test]
+fn test_boundaries_from_empty_pbf() {
+    let mut reader = osmpbfreader::OsmPbfReader::new(std::io::Cursor::new(vec![]));
+    let boundaries = boundaries_from_pbf(&mut reader, |_| true).unwrap();
+    assert_eq!(boundaries.count(), 0);
+}
+
+#[test]
+fn test_boundaries_from_pbf() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let admin_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "1"),
+            named_node(4.0, 4.0, "2"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .relation_id;
+    let other_id = builder
+        .relation()
+        .outer(vec![
+            named_node(5.0, 5.0, "other_start"),
+            named_node(6.0, 5.0, "11"),
+            named_node(6.0, 6.0, "12"),
+            named_node(5.0, 5.0, "other_start"),
+        ])
+        .relation_id;
+    builder.way(vec![named_node(7.0, 7.0, "unused")]);
+    let label = builder.node(geo_types::Point::new(1., 1.), None);
+    for (id, level) in [(admin_id, "8"), (other_id, "9")].iter() {
+        if let osmpbfreader::OsmObj::Relation(ref mut relation) =
+            builder.objects.get_mut(&(*id).into()).unwrap()
+        {
+            relation
+                .tags
+                .insert("boundary".into(), "administrative".into());
+            relation.tags.insert("admin_level".into(), (*level).into());
+            relation.refs.push(osmpbfreader::Ref {
+                member: label.into(),
+                role: "label".into(),
+            });
+        }
+    }
+
+    let pbf = write_pbf(&builder.objects);
+    let mut reader = osmpbfreader::OsmPbfReader::new(std::io::Cursor::new(pbf));
+    let boundaries = BoundaryBuilder::new()
+        .build_from_pbf(&mut reader, is_admin_boundary(8))
+        .unwrap();
+    assert_eq!(boundaries.store.objects.len(), 2);
+    assert_eq!(boundaries.store.nodes.len(), 4);
+    let relation = boundaries.store.objects[&admin_id.into()]
+        .relation()
+        .unwrap();
+    let square = geo_types::Rect::new((0., 0.), (4., 4.)).to_polygon();
+    let points =
+        crate::boundary_points(relation, &MultiPolygon(vec![square]), boundaries.store()).unwrap();
+    assert_eq!(points.label.source, crate::PointSource::Member(label));
+    let boundaries: Vec<_> = boundaries.collect();
+    assert_eq!(boundaries.len(), 1);
+    assert_eq!(boundaries[0].0.id, admin_id);
+    assert_eq!(boundaries[0].0.tags.get("admin_level").unwrap(), "8");
+    assert_eq!(boundaries[0].1.boundary.as_ref().unwrap().0.len(), 1);
+}
+
+#[test]
+fn test_boundaries_from_pbf_with_sub_relations() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let outline_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(4.0, 0.0, "1"),
+            named_node(4.0, 4.0, "2"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .relation_id;
+    let subarea_id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "start"),
+            named_node(2.0, 0.0, "11"),
+            named_node(2.0, 2.0, "12"),
+            named_node(0.0, 0.0, "start"),
+        ])
+        .relation_id;
+    let admin_id = builder
+        .relation()
+        .member("outer", outline_id.into())
+        .member("subarea", subarea_id.into())
+        .relation_id;
+    if let osmpbfreader::OsmObj::Relation(ref mut relation) =
+        builder.objects.get_mut(&admin_id.into()).unwrap()
+    {
+        relation
+            .tags
+            .insert("boundary".into(), "administrative".into());
+        relation.tags.insert("admin_level".into(), "8".into());
+    }
+
+    let pbf = write_pbf(&builder.objects);
+    let mut reader = osmpbfreader::OsmPbfReader::new(std::io::Cursor::new(pbf));
+    let boundaries = BoundaryBuilder::new()
+        .expand_subrelations(2)
+        .build_from_pbf(&mut reader, is_admin_boundary(8))
+        .unwrap();
+    // the subarea is not read, nor its ways and nodes
+    let mut ids: Vec<_> = boundaries.store.objects.keys().cloned().collect();
+    ids.sort();
+    let way = builder.objects[&outline_id.into()].relation().unwrap().refs[0].member;
+    let mut expected = vec![admin_id.into(), outline_id.into(), way];
+    expected.sort();
+    assert_eq!(ids, expected);
+    assert_eq!(boundaries.store.nodes.len(), 3);
+    let boundaries: Vec<_> = boundaries.collect();
+    assert_eq!(boundaries.len(), 1);
+    assert_eq!(boundaries[0].1.boundary.as_ref().unwrap().0.len(), 1);
+}
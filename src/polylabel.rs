@@ -0,0 +1,202 @@
+use geo::{Area, BoundingRect, Centroid, Intersects};
+use geo_types::{Coord, LineString, MultiPolygon, Point, Polygon};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+#[cfg(test)]
+use geo::Contains;
+#[cfg(test)]
+use geo_types::Rect;
+
+/// Square cell of the search grid.
+#[derive(Clone, Copy)]
+struct Cell {
+    center: Coord<f64>,
+    half_size: f64,
+    /// Signed distance from the center to the polygon outline.
+    distance: f64,
+    /// Highest distance that a point of the cell can have.
+    max_distance: f64,
+}
+
+impl Cell {
+    fn new(center: Coord<f64>, half_size: f64, polygon: &Polygon<f64>) -> Self {
+        let distance = signed_distance(center, polygon);
+        Cell {
+            center,
+            half_size,
+            distance,
+            max_distance: distance + half_size * std::f64::consts::SQRT_2,
+        }
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        self.max_distance == other.max_distance
+    }
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.max_distance.total_cmp(&other.max_distance)
+    }
+}
+
+fn segment_distance(p: Coord<f64>, a: Coord<f64>, b: Coord<f64>) -> f64 {
+    let d = b - a;
+    let len = d.x * d.x + d.y * d.y;
+    let t = if len == 0. {
+        0.
+    } else {
+        (((p.x - a.x) * d.x + (p.y - a.y) * d.y) / len).clamp(0., 1.)
+    };
+    let projected = a + d * t;
+    (p.x - projected.x).hypot(p.y - projected.y)
+}
+
+fn ring_distance(p: Coord<f64>, ring: &LineString<f64>) -> f64 {
+    ring.lines()
+        .map(|line| segment_distance(p, line.start, line.end))
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Distance from a point to the outline of a polygon, negative outside.
+fn signed_distance(p: Coord<f64>, polygon: &Polygon<f64>) -> f64 {
+    let distance = polygon
+        .interiors()
+        .iter()
+        .map(|ring| ring_distance(p, ring))
+        .fold(ring_distance(p, polygon.exterior()), f64::min);
+    if polygon.intersects(&p) {
+        distance
+    } else {
+        -distance
+    }
+}
+
+fn polygon_pole(polygon: &Polygon<f64>, precision: f64) -> Option<Point<f64>> {
+    let rect = polygon.bounding_rect()?;
+    // the cells are never smaller than the precision, so that slivers do
+    // not need a huge number of them
+    let cell_size = precision.max(rect.width().min(rect.height()));
+
+    let mut cells = BinaryHeap::new();
+    let half_size = cell_size / 2.;
+    let mut x = rect.min().x;
+    while x < rect.max().x {
+        let mut y = rect.min().y;
+        while y < rect.max().y {
+            let center = Coord {
+                x: x + half_size,
+                y: y + half_size,
+            };
+            cells.push(Cell::new(center, half_size, polygon));
+            y += cell_size;
+        }
+        x += cell_size;
+    }
+
+    let mut best = Cell::new(rect.center(), 0., polygon);
+    if let Some(centroid) = polygon.centroid() {
+        let centroid = Cell::new(centroid.0, 0., polygon);
+        if centroid.distance > best.distance {
+            best = centroid;
+        }
+    }
+
+    while let Some(cell) = cells.pop() {
+        if cell.distance > best.distance {
+            best = cell;
+        }
+        if cell.max_distance - best.distance <= precision {
+            // no cell left in the queue can do better
+            break;
+        }
+        let half_size = cell.half_size / 2.;
+        for (dx, dy) in [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)].iter() {
+            let center = Coord {
+                x: cell.center.x + dx * half_size,
+                y: cell.center.y + dy * half_size,
+            };
+            cells.push(Cell::new(center, half_size, polygon));
+        }
+    }
+    Some(best.center.into())
+}
+
+/// Smallest precision used by `pole_of_inaccessibility`, in degrees (about
+/// 10 cm), as the number of cells needed for a sliver grows with its length
+/// divided by the precision.
+const MIN_PRECISION: f64 = 1e-6;
+
+/// Pole of inaccessibility of a boundary: the point of its largest polygon
+/// the most distant from its outline, found within `precision` (in
+/// degrees, raised to 1e-6 if lower) with the polylabel algorithm.
+///
+/// Unlike the centroid, the point is always inside the boundary (for a
+/// valid boundary). Returns `None` for an empty boundary.
+pub fn pole_of_inaccessibility(
+    multipolygon: &MultiPolygon<f64>,
+    precision: f64,
+) -> Option<Point<f64>> {
+    let precision = precision.max(MIN_PRECISION);
+    let largest = multipolygon
+        .0
+        .iter()
+        .max_by(|a, b| a.unsigned_area().total_cmp(&b.unsigned_area()))?;
+    polygon_pole(largest, precision)
+}
+
+#[test]
+fn test_pole_of_inaccessibility() {
+    // a U, whose centroid is outside, with a small island
+    let u = Polygon::new(
+        vec![
+            (0., 0.),
+            (9., 0.),
+            (9., 9.),
+            (6., 9.),
+            (6., 3.),
+            (3., 3.),
+            (3., 9.),
+            (0., 9.),
+            (0., 0.),
+        ]
+        .into(),
+        vec![],
+    );
+    let island = Rect::new((20., 0.), (21., 1.)).to_polygon();
+    let multipolygon = MultiPolygon(vec![island, u.clone()]);
+    assert!(!u.contains(&u.centroid().unwrap()));
+
+    let pole = pole_of_inaccessibility(&multipolygon, 0.01).unwrap();
+    assert!(u.contains(&pole));
+    // the best points are on the diagonals of the corners of the U, at the
+    // same distance from its outer sides and from its inner corners
+    let best = 3. * 2f64.sqrt() / (1. + 2f64.sqrt());
+    let distance = signed_distance(pole.0, &u);
+    assert!(distance > best - 0.01 && distance <= best + 1e-9);
+
+    assert_eq!(pole_of_inaccessibility(&MultiPolygon(vec![]), 0.01), None);
+}
+
+#[test]
+fn test_pole_of_inaccessibility_of_sliver() {
+    let sliver = Rect::new((0., 0.), (1e-9, 0.01)).to_polygon();
+    let multipolygon = MultiPolygon(vec![sliver.clone()]);
+    for precision in [1e-4, 0., -1., f64::NAN].iter() {
+        let pole = pole_of_inaccessibility(&multipolygon, *precision).unwrap();
+        assert!(sliver.intersects(&pole));
+    }
+    let line = Rect::new((0., 0.), (0., 1.)).to_polygon();
+    assert!(pole_of_inaccessibility(&MultiPolygon(vec![line]), 1e-4).is_some());
+}
@@ -0,0 +1,243 @@
+extern crate osmpbfreader;
+
+use crate::boundaries::BoundaryReport;
+use geo_types::{LineString, MultiPolygon};
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+
+#[cfg(test)]
+use crate::osm_builder;
+#[cfg(test)]
+use crate::osm_builder::named_node;
+#[cfg(test)]
+use crate::BoundaryBuilder;
+
+/// SRID of the WGS 84 coordinates of the boundaries.
+pub const WGS84_SRID: u32 = 4326;
+
+const WKB_MULTIPOLYGON: u32 = 6;
+const WKB_POLYGON: u32 = 3;
+const EWKB_SRID_FLAG: u32 = 0x2000_0000;
+
+/// Format of the tags column written by `write_copy_rows`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyTags {
+    Hstore,
+    Json,
+}
+
+fn write_wkt_ring(wkt: &mut String, ring: &LineString<f64>) {
+    wkt.push('(');
+    for (i, c) in ring.0.iter().enumerate() {
+        if i > 0 {
+            wkt.push(',');
+        }
+        write!(wkt, "{} {}", c.x, c.y).unwrap();
+    }
+    wkt.push(')');
+}
+
+/// Encodes a boundary as WKT.
+pub fn to_wkt(multipolygon: &MultiPolygon<f64>) -> String {
+    if multipolygon.0.is_empty() {
+        return "MULTIPOLYGON EMPTY".into();
+    }
+    let mut wkt = "MULTIPOLYGON(".to_string();
+    for (i, polygon) in multipolygon.0.iter().enumerate() {
+        if i > 0 {
+            wkt.push(',');
+        }
+        wkt.push('(');
+        write_wkt_ring(&mut wkt, polygon.exterior());
+        for interior in polygon.interiors() {
+            wkt.push(',');
+            write_wkt_ring(&mut wkt, interior);
+        }
+        wkt.push(')');
+    }
+    wkt.push(')');
+    wkt
+}
+
+/// Encodes a boundary as PostGIS EWKT, tagged with the WGS 84 SRID.
+pub fn to_ewkt(multipolygon: &MultiPolygon<f64>) -> String {
+    format!("SRID={};{}", WGS84_SRID, to_wkt(multipolygon))
+}
+
+fn write_wkb_ring(wkb: &mut Vec<u8>, ring: &LineString<f64>) {
+    wkb.extend(&(ring.0.len() as u32).to_le_bytes());
+    for c in &ring.0 {
+        wkb.extend(&c.x.to_le_bytes());
+        wkb.extend(&c.y.to_le_bytes());
+    }
+}
+
+fn encode_wkb(multipolygon: &MultiPolygon<f64>, srid: Option<u32>) -> Vec<u8> {
+    let mut wkb = vec![1];
+    match srid {
+        Some(srid) => {
+            wkb.extend(&(WKB_MULTIPOLYGON | EWKB_SRID_FLAG).to_le_bytes());
+            wkb.extend(&srid.to_le_bytes());
+        }
+        None => wkb.extend(&WKB_MULTIPOLYGON.to_le_bytes()),
+    }
+    wkb.extend(&(multipolygon.0.len() as u32).to_le_bytes());
+    for polygon in &multipolygon.0 {
+        wkb.push(1);
+        wkb.extend(&WKB_POLYGON.to_le_bytes());
+        wkb.extend(&(1 + polygon.interiors().len() as u32).to_le_bytes());
+        write_wkb_ring(&mut wkb, polygon.exterior());
+        for interior in polygon.interiors() {
+            write_wkb_ring(&mut wkb, interior);
+        }
+    }
+    wkb
+}
+
+/// Encodes a boundary as little endian WKB.
+pub fn to_wkb(multipolygon: &MultiPolygon<f64>) -> Vec<u8> {
+    encode_wkb(multipolygon, None)
+}
+
+/// Encodes a boundary as little endian PostGIS EWKB, tagged with the
+/// WGS 84 SRID.
+pub fn to_ewkb(multipolygon: &MultiPolygon<f64>) -> Vec<u8> {
+    encode_wkb(multipolygon, Some(WGS84_SRID))
+}
+
+fn push_escaped(out: &mut String, s: &str, json: bool) {
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if json && (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+}
+
+fn format_tags(tags: &osmpbfreader::Tags, format: CopyTags) -> String {
+    let mut out = String::new();
+    if format == CopyTags::Json {
+        out.push('{');
+    }
+    for (i, (k, v)) in tags.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        let json = format == CopyTags::Json;
+        out.push('"');
+        push_escaped(&mut out, k, json);
+        out.push_str(if json { "\": \"" } else { "\"=>\"" });
+        push_escaped(&mut out, v, json);
+        out.push('"');
+    }
+    if format == CopyTags::Json {
+        out.push('}');
+    }
+    out
+}
+
+fn push_copy_escaped(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Writes the boundaries in the PostgreSQL `COPY` text format, one
+/// `relation_id, tags, geometry` row per boundary, the geometry being hex
+/// encoded EWKB.
+///
+/// The boundaries that could not be built are skipped.
+pub fn write_copy_rows<'a, W, I>(mut writer: W, boundaries: I, tags: CopyTags) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = (&'a osmpbfreader::Relation, &'a BoundaryReport)>,
+{
+    let mut row = String::new();
+    for (relation, report) in boundaries {
+        let multipolygon = match report.boundary {
+            Ok(ref multipolygon) => multipolygon,
+            Err(_) => continue,
+        };
+        row.clear();
+        write!(row, "{}\t", relation.id.0).unwrap();
+        push_copy_escaped(&mut row, &format_tags(&relation.tags, tags));
+        row.push('\t');
+        for byte in to_ewkb(multipolygon) {
+            write!(row, "{:02X}", byte).unwrap();
+        }
+        row.push('\n');
+        writer.write_all(row.as_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn square() -> MultiPolygon<f64> {
+    MultiPolygon(vec![geo_types::Polygon::new(
+        vec![(0., 0.), (1., 0.), (1., 1.), (0., 1.), (0., 0.)].into(),
+        vec![],
+    )])
+}
+
+#[test]
+fn test_wkt() {
+    assert_eq!(to_wkt(&MultiPolygon(vec![])), "MULTIPOLYGON EMPTY");
+    assert_eq!(
+        to_ewkt(&square()),
+        "SRID=4326;MULTIPOLYGON(((0 0,1 0,1 1,0 1,0 0)))"
+    );
+}
+
+#[test]
+fn test_wkb() {
+    let wkb = to_wkb(&square());
+    let ewkb = to_ewkb(&square());
+    assert_eq!(wkb.len(), 1 + 4 + 4 + 1 + 4 + 4 + 4 + 5 * 16);
+    assert_eq!(&wkb[..9], &[1, 6, 0, 0, 0, 1, 0, 0, 0]);
+    assert_eq!(&ewkb[..9], &[1, 6, 0, 0, 0x20, 0xE6, 0x10, 0, 0]);
+    assert_eq!(&ewkb[9..], &wkb[5..]);
+}
+
+#[test]
+fn test_write_copy_rows() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let id = builder
+        .relation()
+        .outer(vec![
+            named_node(0.0, 0.0, "a"),
+            named_node(1.0, 0.0, "b"),
+            named_node(1.0, 1.0, "c"),
+            named_node(0.0, 0.0, "a"),
+        ])
+        .relation_id;
+    let mut relation = builder.objects[&id.into()].relation().unwrap().clone();
+    relation.tags.insert("name".into(), "a \"b\"\tc".into());
+    let report = BoundaryBuilder::new().build(&relation, &builder.objects);
+    let hex: String = to_ewkb(report.boundary.as_ref().unwrap())
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect();
+
+    let mut rows = vec![];
+    write_copy_rows(&mut rows, Some((&relation, &report)), CopyTags::Hstore).unwrap();
+    assert_eq!(
+        String::from_utf8(rows).unwrap(),
+        format!("0\t\"name\"=>\"a \\\\\"b\\\\\"\\tc\"\t{}\n", hex)
+    );
+    let mut rows = vec![];
+    write_copy_rows(&mut rows, Some((&relation, &report)), CopyTags::Json).unwrap();
+    assert_eq!(
+        String::from_utf8(rows).unwrap(),
+        format!("0\t{{\"name\": \"a \\\\\"b\\\\\"\\\\u0009c\"}}\t{}\n", hex)
+    );
+}
@@ -0,0 +1,384 @@
+extern crate osmpbfreader;
+
+use geo_types::Coord;
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+use std::iter::FromIterator;
+
+#[cfg(test)]
+use crate::osm_builder;
+#[cfg(test)]
+use crate::osm_builder::named_node;
+
+/// Gives the location of nodes, as `x: lon, y: lat`.
+pub trait NodeLocationStore {
+    fn node_location(&self, id: osmpbfreader::NodeId) -> Option<Coord<f64>>;
+}
+
+/// Gives the objects a boundary is built from.
+pub trait OsmObjectStore: NodeLocationStore {
+    fn way(&self, id: osmpbfreader::WayId) -> Option<Cow<'_, osmpbfreader::Way>>;
+    fn relation(&self, id: osmpbfreader::RelationId) -> Option<Cow<'_, osmpbfreader::Relation>>;
+}
+
+fn node_location(obj: &osmpbfreader::OsmObj) -> Option<Coord<f64>> {
+    obj.node().map(|node| Coord {
+        x: node.lon(),
+        y: node.lat(),
+    })
+}
+
+impl<T: Borrow<osmpbfreader::OsmObj>> NodeLocationStore for BTreeMap<osmpbfreader::OsmId, T> {
+    fn node_location(&self, id: osmpbfreader::NodeId) -> Option<Coord<f64>> {
+        self.get(&id.into())
+            .and_then(|obj| node_location(obj.borrow()))
+    }
+}
+
+impl<T: Borrow<osmpbfreader::OsmObj>> OsmObjectStore for BTreeMap<osmpbfreader::OsmId, T> {
+    fn way(&self, id: osmpbfreader::WayId) -> Option<Cow<'_, osmpbfreader::Way>> {
+        self.get(&id.into())
+            .and_then(|obj| obj.borrow().way())
+            .map(Cow::Borrowed)
+    }
+
+    fn relation(&self, id: osmpbfreader::RelationId) -> Option<Cow<'_, osmpbfreader::Relation>> {
+        self.get(&id.into())
+            .and_then(|obj| obj.borrow().relation())
+            .map(Cow::Borrowed)
+    }
+}
+
+impl<T, S> NodeLocationStore for HashMap<osmpbfreader::OsmId, T, S>
+where
+    T: Borrow<osmpbfreader::OsmObj>,
+    S: BuildHasher,
+{
+    fn node_location(&self, id: osmpbfreader::NodeId) -> Option<Coord<f64>> {
+        self.get(&id.into())
+            .and_then(|obj| node_location(obj.borrow()))
+    }
+}
+
+impl<T, S> OsmObjectStore for HashMap<osmpbfreader::OsmId, T, S>
+where
+    T: Borrow<osmpbfreader::OsmObj>,
+    S: BuildHasher,
+{
+    fn way(&self, id: osmpbfreader::WayId) -> Option<Cow<'_, osmpbfreader::Way>> {
+        self.get(&id.into())
+            .and_then(|obj| obj.borrow().way())
+            .map(Cow::Borrowed)
+    }
+
+    fn relation(&self, id: osmpbfreader::RelationId) -> Option<Cow<'_, osmpbfreader::Relation>> {
+        self.get(&id.into())
+            .and_then(|obj| obj.borrow().relation())
+            .map(Cow::Borrowed)
+    }
+}
+
+impl<N: NodeLocationStore + ?Sized> NodeLocationStore for &N {
+    fn node_location(&self, id: osmpbfreader::NodeId) -> Option<Coord<f64>> {
+        (**self).node_location(id)
+    }
+}
+
+impl<O: OsmObjectStore + ?Sized> OsmObjectStore for &O {
+    fn way(&self, id: osmpbfreader::WayId) -> Option<Cow<'_, osmpbfreader::Way>> {
+        (**self).way(id)
+    }
+
+    fn relation(&self, id: osmpbfreader::RelationId) -> Option<Cow<'_, osmpbfreader::Relation>> {
+        (**self).relation(id)
+    }
+}
+
+/// Node locations stored as a vector sorted by id, taking 16 bytes per node.
+#[derive(Debug, Clone, Default)]
+pub struct SortedNodeLocations {
+    nodes: Vec<(osmpbfreader::NodeId, i32, i32)>,
+}
+
+impl SortedNodeLocations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node, which must have a greater id than the nodes already added
+    /// (as when reading them from a pbf file).
+    pub fn push(&mut self, node: &osmpbfreader::Node) {
+        debug_assert!(self.nodes.last().is_none_or(|last| last.0 < node.id));
+        self.nodes
+            .push((node.id, node.decimicro_lat, node.decimicro_lon));
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl FromIterator<osmpbfreader::Node> for SortedNodeLocations {
+    fn from_iter<I: IntoIterator<Item = osmpbfreader::Node>>(nodes: I) -> Self {
+        let mut nodes: Vec<_> = nodes
+            .into_iter()
+            .map(|node| (node.id, node.decimicro_lat, node.decimicro_lon))
+            .collect();
+        nodes.sort_by_key(|node| node.0);
+        nodes.dedup_by_key(|node| node.0);
+        SortedNodeLocations { nodes }
+    }
+}
+
+impl NodeLocationStore for SortedNodeLocations {
+    fn node_location(&self, id: osmpbfreader::NodeId) -> Option<Coord<f64>> {
+        self.nodes
+            .binary_search_by_key(&id, |node| node.0)
+            .ok()
+            .map(|idx| {
+                let (_, lat, lon) = self.nodes[idx];
+                Coord {
+                    x: lon as f64 * 1e-7,
+                    y: lat as f64 * 1e-7,
+                }
+            })
+    }
+}
+
+/// Node locations stored in a flat array indexed by node id, taking 8 bytes
+/// per id up to the greatest one.
+///
+/// Coordinates are stored with their sign bit flipped, so that zeroed memory
+/// (e.g. a sparse file) means no node.
+#[derive(Debug, Clone, Default)]
+pub struct DenseNodeLocations<D = Vec<u8>> {
+    data: D,
+}
+
+const DENSE_ENTRY_SIZE: usize = 8;
+
+fn encode_decimicro(value: i32) -> [u8; 4] {
+    ((value as u32) ^ 0x8000_0000).to_le_bytes()
+}
+
+fn decode_decimicro(bytes: &[u8]) -> i32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(bytes);
+    (u32::from_le_bytes(buf) ^ 0x8000_0000) as i32
+}
+
+fn dense_offset(id: osmpbfreader::NodeId) -> Option<usize> {
+    if id.0 < 0 {
+        return None;
+    }
+    (id.0 as usize).checked_mul(DENSE_ENTRY_SIZE)
+}
+
+fn write_dense_entry(data: &mut [u8], offset: usize, node: &osmpbfreader::Node) {
+    data[offset..offset + 4].copy_from_slice(&encode_decimicro(node.decimicro_lat));
+    data[offset + 4..offset + 8].copy_from_slice(&encode_decimicro(node.decimicro_lon));
+}
+
+impl<D> DenseNodeLocations<D> {
+    /// Uses `data` as the array, e.g. a memory-mapped file written before.
+    pub fn from_data(data: D) -> Self {
+        DenseNodeLocations { data }
+    }
+
+    pub fn into_data(self) -> D {
+        self.data
+    }
+}
+
+impl DenseNodeLocations<Vec<u8>> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node, growing the array up to its id if needed.
+    /// Nodes with a negative id are ignored.
+    pub fn insert(&mut self, node: &osmpbfreader::Node) {
+        if let Some(offset) = dense_offset(node.id) {
+            if self.data.len() < offset + DENSE_ENTRY_SIZE {
+                self.data.resize(offset + DENSE_ENTRY_SIZE, 0);
+            }
+            write_dense_entry(&mut self.data, offset, node);
+        }
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl DenseNodeLocations<memmap2::MmapMut> {
+    /// Creates the array in a file able to hold the nodes with an id up to
+    /// `max_id`. The file is sparse on most file systems, so only the pages
+    /// holding nodes take disk space.
+    pub fn create<P: AsRef<std::path::Path>>(path: P, max_id: u64) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len((max_id + 1) * DENSE_ENTRY_SIZE as u64)?;
+        let data = unsafe { memmap2::MmapMut::map_mut(&file)? };
+        Ok(DenseNodeLocations { data })
+    }
+
+    /// Adds a node.
+    ///
+    /// Panics if its id is greater than the `max_id` given to `create`.
+    pub fn insert(&mut self, node: &osmpbfreader::Node) {
+        if let Some(offset) = dense_offset(node.id) {
+            write_dense_entry(&mut self.data, offset, node);
+        }
+    }
+
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.data.flush()
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl DenseNodeLocations<memmap2::Mmap> {
+    /// Opens, read only, an array written by `DenseNodeLocations::create`.
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let data = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(DenseNodeLocations { data })
+    }
+}
+
+impl FromIterator<osmpbfreader::Node> for DenseNodeLocations<Vec<u8>> {
+    fn from_iter<I: IntoIterator<Item = osmpbfreader::Node>>(nodes: I) -> Self {
+        let mut locations = Self::new();
+        for node in nodes {
+            locations.insert(&node);
+        }
+        locations
+    }
+}
+
+impl<D: AsRef<[u8]>> NodeLocationStore for DenseNodeLocations<D> {
+    fn node_location(&self, id: osmpbfreader::NodeId) -> Option<Coord<f64>> {
+        let offset = dense_offset(id)?;
+        let entry = self
+            .data
+            .as_ref()
+            .get(offset..offset.checked_add(DENSE_ENTRY_SIZE)?)?;
+        if entry.iter().all(|&b| b == 0) {
+            return None;
+        }
+        Some(Coord {
+            x: decode_decimicro(&entry[4..]) as f64 * 1e-7,
+            y: decode_decimicro(&entry[..4]) as f64 * 1e-7,
+        })
+    }
+}
+
+/// Store taking the ways and relations from `objects`, and the node
+/// locations from `nodes`, e.g. to use a compact node index.
+#[derive(Debug, Clone, Default)]
+pub struct SplitStore<O, N> {
+    pub objects: O,
+    pub nodes: N,
+}
+
+impl<O, N: NodeLocationStore> NodeLocationStore for SplitStore<O, N> {
+    fn node_location(&self, id: osmpbfreader::NodeId) -> Option<Coord<f64>> {
+        self.nodes.node_location(id)
+    }
+}
+
+impl<O: OsmObjectStore, N: NodeLocationStore> OsmObjectStore for SplitStore<O, N> {
+    fn way(&self, id: osmpbfreader::WayId) -> Option<Cow<'_, osmpbfreader::Way>> {
+        self.objects.way(id)
+    }
+
+    fn relation(&self, id: osmpbfreader::RelationId) -> Option<Cow<'_, osmpbfreader::Relation>> {
+        self.objects.relation(id)
+    }
+}
+
+#[test]
+fn test_sorted_node_locations() {
+    let node = |id: i64, lon: f64, lat: f64| osmpbfreader::Node {
+        id: osmpbfreader::NodeId(id),
+        decimicro_lat: (lat * 1e7) as i32,
+        decimicro_lon: (lon * 1e7) as i32,
+        tags: osmpbfreader::Tags::new(),
+    };
+    let nodes: SortedNodeLocations = vec![node(12, 3.7, 1.2), node(8, 63.5, 5.5), node(12, 0., 0.)]
+        .into_iter()
+        .collect();
+    assert_eq!(nodes.len(), 2);
+    let location = nodes.node_location(osmpbfreader::NodeId(8)).unwrap();
+    assert!((location.x - 63.5).abs() < 1e-7);
+    assert!((location.y - 5.5).abs() < 1e-7);
+    assert!(nodes.node_location(osmpbfreader::NodeId(12)).is_some());
+    assert!(nodes.node_location(osmpbfreader::NodeId(10)).is_none());
+}
+
+#[test]
+fn test_dense_node_locations() {
+    let node = |id: i64, lon: f64, lat: f64| osmpbfreader::Node {
+        id: osmpbfreader::NodeId(id),
+        decimicro_lat: (lat * 1e7) as i32,
+        decimicro_lon: (lon * 1e7) as i32,
+        tags: osmpbfreader::Tags::new(),
+    };
+    let nodes: DenseNodeLocations = vec![node(12, -3.7, 1.2), node(8, 0., 0.), node(-4, 1., 1.)]
+        .into_iter()
+        .collect();
+    let location = nodes.node_location(osmpbfreader::NodeId(12)).unwrap();
+    assert!((location.x + 3.7).abs() < 1e-7);
+    assert!((location.y - 1.2).abs() < 1e-7);
+    assert_eq!(
+        nodes.node_location(osmpbfreader::NodeId(8)),
+        Some(Coord { x: 0., y: 0. })
+    );
+    assert!(nodes.node_location(osmpbfreader::NodeId(10)).is_none());
+    assert!(nodes.node_location(osmpbfreader::NodeId(13)).is_none());
+    assert!(nodes.node_location(osmpbfreader::NodeId(-4)).is_none());
+}
+
+#[test]
+fn test_hash_map_store() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let way_id = builder.way(vec![
+        named_node(3.4, 5.2, "start"),
+        named_node(5.4, 5.1, "1"),
+    ]);
+    let objects: HashMap<_, _> = builder.objects.clone().into_iter().collect();
+    assert_eq!(objects.way(way_id).unwrap().nodes.len(), 2);
+    assert!(objects.relation(osmpbfreader::RelationId(0)).is_none());
+    let location = objects.node_location(osmpbfreader::NodeId(1)).unwrap();
+    assert!((location.x - 5.4).abs() < 1e-7);
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mmap_dense_node_locations() {
+    let path = std::env::temp_dir().join(format!("dense_nodes_{}", std::process::id()));
+    let mut nodes = DenseNodeLocations::create(&path, 100).unwrap();
+    nodes.insert(&osmpbfreader::Node {
+        id: osmpbfreader::NodeId(42),
+        decimicro_lat: 12_000_000,
+        decimicro_lon: -37_000_000,
+        tags: osmpbfreader::Tags::new(),
+    });
+    nodes.flush().unwrap();
+    drop(nodes);
+
+    let nodes = DenseNodeLocations::open(&path).unwrap();
+    let location = nodes.node_location(osmpbfreader::NodeId(42)).unwrap();
+    assert!((location.x + 3.7).abs() < 1e-7);
+    assert!((location.y - 1.2).abs() < 1e-7);
+    assert!(nodes.node_location(osmpbfreader::NodeId(41)).is_none());
+    assert!(nodes.node_location(osmpbfreader::NodeId(101)).is_none());
+    std::fs::remove_file(&path).unwrap();
+}
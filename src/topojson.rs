@@ -0,0 +1,152 @@
+extern crate osmpbfreader;
+
+use crate::topology::{ArcRef, Topology};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+#[cfg(test)]
+use crate::boundaries::BoundaryBuilder;
+#[cfg(test)]
+use crate::osm_builder;
+#[cfg(test)]
+use crate::osm_builder::named_node;
+
+fn arc_index(arc_ref: &ArcRef) -> i64 {
+    // TopoJSON references an arc walked backwards by its ones' complement
+    if arc_ref.reversed {
+        !(arc_ref.arc as i64)
+    } else {
+        arc_ref.arc as i64
+    }
+}
+
+/// Converts a topology to a TopoJSON object, with the boundaries as the
+/// `MultiPolygon` geometries of its `boundaries` object.
+///
+/// The geometries have the id of their relation, and the tags of the
+/// relation in `relations` (if any) as properties. The coordinates are
+/// not quantized.
+pub fn to_topojson(topology: &Topology, relations: &[osmpbfreader::Relation]) -> Value {
+    let tags: BTreeMap<_, _> = relations.iter().map(|r| (r.id, &r.tags)).collect();
+    let arcs: Vec<Value> = topology
+        .arcs
+        .iter()
+        .map(|arc| {
+            Value::from(
+                arc.coords
+                    .0
+                    .iter()
+                    .map(|c| vec![c.x, c.y])
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    let geometries: Vec<Value> = topology
+        .boundaries
+        .iter()
+        .map(|(id, polygons)| {
+            let arcs: Vec<Vec<Vec<i64>>> = polygons
+                .iter()
+                .map(|polygon| {
+                    polygon
+                        .iter()
+                        .map(|ring| ring.iter().map(arc_index).collect())
+                        .collect()
+                })
+                .collect();
+            let properties: Map<String, Value> = tags
+                .get(id)
+                .map(|tags| {
+                    tags.iter()
+                        .map(|(k, v)| (k.to_string(), Value::from(v.as_str())))
+                        .collect()
+                })
+                .unwrap_or_default();
+            serde_json::json!({
+                "type": "MultiPolygon",
+                "id": id.0,
+                "properties": properties,
+                "arcs": arcs,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "type": "Topology",
+        "objects": {
+            "boundaries": {
+                "type": "GeometryCollection",
+                "geometries": geometries,
+            }
+        },
+        "arcs": arcs,
+    })
+}
+
+/// Writes a topology as TopoJSON, see `to_topojson`.
+pub fn write_topojson<W: Write>(
+    writer: W,
+    topology: &Topology,
+    relations: &[osmpbfreader::Relation],
+) -> io::Result<()> {
+    serde_json::to_writer(writer, &to_topojson(topology, relations)).map_err(io::Error::from)
+}
+
+#[test]
+fn test_to_topojson() {
+    let mut builder = osm_builder::OsmBuilder::new();
+    let border = vec![named_node(2.0, 0.0, "bottom"), named_node(2.0, 2.0, "top")];
+    let left = builder
+        .relation()
+        .outer(border.clone())
+        .outer(vec![
+            named_node(2.0, 2.0, "top"),
+            named_node(0.0, 2.0, "a"),
+            named_node(0.0, 0.0, "b"),
+            named_node(2.0, 0.0, "bottom"),
+        ])
+        .relation_id;
+    let right = builder
+        .relation()
+        .outer(border)
+        .outer(vec![
+            named_node(2.0, 2.0, "top"),
+            named_node(4.0, 2.0, "c"),
+            named_node(4.0, 0.0, "d"),
+            named_node(2.0, 0.0, "bottom"),
+        ])
+        .relation_id;
+    if let osmpbfreader::OsmObj::Relation(ref mut relation) =
+        builder.objects.get_mut(&left.into()).unwrap()
+    {
+        relation.tags.insert("name".into(), "left".into());
+    }
+    let relations: Vec<_> = [left, right]
+        .iter()
+        .map(|id| builder.objects[&(*id).into()].relation().unwrap().clone())
+        .collect();
+    let rings: Vec<_> = relations
+        .iter()
+        .map(|r| {
+            let rings = BoundaryBuilder::new().build_rings(r, &builder.objects);
+            (r.id, rings.boundary.unwrap())
+        })
+        .collect();
+    let topology = Topology::new(rings.iter().map(|(id, rings)| (*id, rings)));
+
+    let mut json = vec![];
+    write_topojson(&mut json, &topology, &relations[..1]).unwrap();
+    let json: Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["type"], "Topology");
+    assert_eq!(json["arcs"].as_array().unwrap().len(), 3);
+    let geometries = json["objects"]["boundaries"]["geometries"]
+        .as_array()
+        .unwrap();
+    assert_eq!(geometries.len(), 2);
+    assert_eq!(geometries[0]["id"], left.0);
+    assert_eq!(geometries[0]["properties"]["name"], "left");
+    assert_eq!(geometries[1]["properties"], serde_json::json!({}));
+    // the border is a single arc referenced by both boundaries
+    assert_eq!(geometries[0]["arcs"], serde_json::json!([[[0, -2]]]));
+    assert_eq!(geometries[1]["arcs"], serde_json::json!([[[0, -3]]]));
+}
@@ -0,0 +1,447 @@
+extern crate osmpbfreader;
+
+use crate::boundaries::{BoundaryRings, TracedRing};
+use geo_types::{Coord, LineString, MultiPolygon, Polygon};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+#[cfg(test)]
+use crate::boundaries::BoundaryBuilder;
+#[cfg(test)]
+use crate::osm_builder;
+#[cfg(test)]
+use crate::osm_builder::named_node;
+
+/// Algorithm used to simplify the arcs of a `Topology`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Simplification {
+    /// Ramer–Douglas–Peucker, the tolerance being a distance.
+    DouglasPeucker,
+    /// Visvalingam-Whyatt, the tolerance being an area.
+    Visvalingam,
+}
+
+/// A line of a `Topology`, going from a junction node to another one, or
+/// closed if it meets no junction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopologyArc {
+    pub nodes: Vec<osmpbfreader::NodeId>,
+    pub coords: LineString<f64>,
+}
+
+/// Reference to an arc of a `Topology`, possibly walked backwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArcRef {
+    pub arc: usize,
+    pub reversed: bool,
+}
+
+/// A ring of a `Topology`, as the arcs it is made of.
+pub type ArcRing = Vec<ArcRef>;
+
+/// A polygon of a `Topology`, its exterior ring being the first one.
+pub type ArcPolygon = Vec<ArcRing>;
+
+/// Boundaries split into arcs at their junctions (the nodes where at least
+/// three segments meet), so that the borders shared by several boundaries
+/// are stored once.
+///
+/// Simplifying the arcs rather than each boundary keeps the neighbouring
+/// boundaries fitting exactly, without gaps or overlaps between them.
+///
+/// The vertices without an OSM node (with a negative id, see
+/// `RingAssembly::Faces`) are numbered again by their location, as their
+/// ids are only unique within a boundary.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Topology {
+    pub arcs: Vec<TopologyArc>,
+    pub boundaries: BTreeMap<osmpbfreader::RelationId, Vec<ArcPolygon>>,
+}
+
+impl Topology {
+    /// Builds the topology of boundaries built by `BoundaryBuilder::build_rings`.
+    pub fn new<'a, I>(boundaries: I) -> Self
+    where
+        I: IntoIterator<Item = (osmpbfreader::RelationId, &'a BoundaryRings)>,
+    {
+        let boundaries: Vec<_> = boundaries.into_iter().collect();
+        let rings = || {
+            boundaries.iter().flat_map(|(_, b)| {
+                b.polygons
+                    .iter()
+                    .flat_map(|p| std::iter::once(&p.exterior).chain(p.interiors.iter()))
+            })
+        };
+
+        let mut synthetic = HashMap::new();
+        for ring in rings() {
+            for (node, coord) in ring.nodes.iter().zip(&ring.coords.0) {
+                if node.0 < 0 {
+                    let id = osmpbfreader::NodeId(-(synthetic.len() as i64) - 1);
+                    synthetic
+                        .entry((coord.x.to_bits(), coord.y.to_bits()))
+                        .or_insert(id);
+                }
+            }
+        }
+        let ring_nodes = |ring: &TracedRing| -> Vec<osmpbfreader::NodeId> {
+            ring.nodes
+                .iter()
+                .zip(&ring.coords.0)
+                .map(|(node, coord)| match node.0 {
+                    id if id < 0 => synthetic[&(coord.x.to_bits(), coord.y.to_bits())],
+                    _ => *node,
+                })
+                .collect()
+        };
+
+        let mut neighbors: HashMap<osmpbfreader::NodeId, BTreeSet<osmpbfreader::NodeId>> =
+            HashMap::new();
+        let mut coords = HashMap::new();
+        for ring in rings() {
+            for (i, w) in ring_nodes(ring).windows(2).enumerate() {
+                neighbors.entry(w[0]).or_default().insert(w[1]);
+                neighbors.entry(w[1]).or_default().insert(w[0]);
+                coords.entry(w[0]).or_insert(ring.coords.0[i]);
+            }
+        }
+        let junctions: BTreeSet<_> = neighbors
+            .into_iter()
+            .filter(|(_, around)| around.len() > 2)
+            .map(|(node, _)| node)
+            .collect();
+
+        let mut topology = Topology::default();
+        let mut arc_idx: HashMap<Vec<osmpbfreader::NodeId>, usize> = HashMap::new();
+        for (id, boundary) in &boundaries {
+            let polygons = boundary
+                .polygons
+                .iter()
+                .map(|p| {
+                    std::iter::once(&p.exterior)
+                        .chain(p.interiors.iter())
+                        .map(|ring| {
+                            split_ring(&ring_nodes(ring), &junctions)
+                                .into_iter()
+                                .map(|nodes| topology.arc_ref(nodes, &coords, &mut arc_idx))
+                                .collect()
+                        })
+                        .collect()
+                })
+                .collect();
+            topology.boundaries.insert(*id, polygons);
+        }
+        topology
+    }
+
+    /// Reference to the arc made of `nodes`, added if it is not known yet.
+    fn arc_ref(
+        &mut self,
+        nodes: Vec<osmpbfreader::NodeId>,
+        coords: &HashMap<osmpbfreader::NodeId, Coord<f64>>,
+        arc_idx: &mut HashMap<Vec<osmpbfreader::NodeId>, usize>,
+    ) -> ArcRef {
+        let (key, reversed) = canonical_arc(&nodes);
+        let arcs = &mut self.arcs;
+        let arc = *arc_idx.entry(key).or_insert_with_key(|key| {
+            arcs.push(TopologyArc {
+                nodes: key.clone(),
+                coords: key.iter().map(|n| coords[n]).collect(),
+            });
+            arcs.len() - 1
+        });
+        ArcRef { arc, reversed }
+    }
+
+    /// Simplifies each arc once, keeping its ends, `tolerance` being in the
+    /// unit of the coordinates (degrees) or its square, see `Simplification`.
+    pub fn simplify(&self, tolerance: f64, algorithm: Simplification) -> Topology {
+        let arcs = self
+            .arcs
+            .iter()
+            .map(|arc| {
+                let n = arc.nodes.len();
+                let mut kept = if arc.nodes[0] == arc.nodes[n - 1] && n > 2 {
+                    // a closed arc is simplified as two halves, so that it
+                    // does not collapse into its start
+                    let mid = n / 2;
+                    let mut kept = simplify_idx(&arc.coords.0[..=mid], tolerance, algorithm);
+                    kept.pop();
+                    kept.extend(
+                        simplify_idx(&arc.coords.0[mid..], tolerance, algorithm)
+                            .into_iter()
+                            .map(|i| i + mid),
+                    );
+                    kept
+                } else {
+                    simplify_idx(&arc.coords.0, tolerance, algorithm)
+                };
+                kept.dedup();
+                TopologyArc {
+                    nodes: kept.iter().map(|&i| arc.nodes[i]).collect(),
+                    coords: kept.iter().map(|&i| arc.coords.0[i]).collect(),
+                }
+            })
+            .collect();
+        Topology {
+            arcs,
+            boundaries: self.boundaries.clone(),
+        }
+    }
+
+    fn ring_line_string(&self, ring: &[ArcRef]) -> LineString<f64> {
+        let mut coords: Vec<Coord<f64>> = vec![];
+        for arc_ref in ring {
+            let arc = &self.arcs[arc_ref.arc].coords.0;
+            let arc_coords: Box<dyn Iterator<Item = &Coord<f64>>> = if arc_ref.reversed {
+                Box::new(arc.iter().rev())
+            } else {
+                Box::new(arc.iter())
+            };
+            // consecutive arcs share their end
+            let skip = if coords.is_empty() { 0 } else { 1 };
+            coords.extend(arc_coords.skip(skip));
+        }
+        LineString(coords)
+    }
+
+    /// Reassembles a boundary from its arcs. The rings collapsed by a
+    /// simplification are dropped, with their polygon for an exterior.
+    pub fn multipolygon(&self, id: osmpbfreader::RelationId) -> Option<MultiPolygon<f64>> {
+        let polygons = self.boundaries.get(&id)?;
+        let is_ring = |ring: &LineString<f64>| ring.0.len() >= 4;
+        Some(
+            polygons
+                .iter()
+                .filter_map(|polygon| {
+                    let exterior = self.ring_line_string(&polygon[0]);
+                    if !is_ring(&exterior) {
+                        return None;
+                    }
+                    let interiors = polygon[1..]
+                        .iter()
+                        .map(|ring| self.ring_line_string(ring))
+                        .filter(is_ring)
+                        .collect();
+                    Some(Polygon::new(exterior, interiors))
+                })
+                .collect(),
+        )
+    }
+
+    pub fn multipolygons(&self) -> BTreeMap<osmpbfreader::RelationId, MultiPolygon<f64>> {
+        self.boundaries
+            .keys()
+            .filter_map(|id| Some((*id, self.multipolygon(*id)?)))
+            .collect()
+    }
+}
+
+fn simplify_idx(coords: &[Coord<f64>], tolerance: f64, algorithm: Simplification) -> Vec<usize> {
+    use geo::{SimplifyIdx, SimplifyVwIdx};
+    let line_string = LineString(coords.to_vec());
+    match algorithm {
+        Simplification::DouglasPeucker => line_string.simplify_idx(&tolerance),
+        Simplification::Visvalingam => line_string.simplify_vw_idx(&tolerance),
+    }
+}
+
+/// Splits the nodes of a closed ring into the node lists of its arcs, cut
+/// at the junctions.
+fn split_ring(
+    nodes: &[osmpbfreader::NodeId],
+    junctions: &BTreeSet<osmpbfreader::NodeId>,
+) -> Vec<Vec<osmpbfreader::NodeId>> {
+    let open = &nodes[..nodes.len() - 1];
+    let start = match open.iter().position(|n| junctions.contains(n)) {
+        Some(start) => start,
+        None => return vec![nodes.to_vec()],
+    };
+    let mut arcs = vec![];
+    let mut arc = vec![open[start]];
+    for i in 1..=open.len() {
+        let node = open[(start + i) % open.len()];
+        arc.push(node);
+        if junctions.contains(&node) {
+            arcs.push(std::mem::replace(&mut arc, vec![node]));
+        }
+    }
+    arcs
+}
+
+/// The same key for an arc walked in either direction (and from any node
+/// for a closed arc), and whether `nodes` walks it backwards.
+fn canonical_arc(nodes: &[osmpbfreader::NodeId]) -> (Vec<osmpbfreader::NodeId>, bool) {
+    let n = nodes.len();
+    if n > 2 && nodes[0] == nodes[n - 1] {
+        let open = &nodes[..n - 1];
+        let start = (0..open.len()).min_by_key(|&i| open[i]).unwrap();
+        let close = |mut v: Vec<osmpbfreader::NodeId>| {
+            v.push(v[0]);
+            v
+        };
+        let forward = close(
+            (0..open.len())
+                .map(|i| open[(start + i) % open.len()])
+                .collect(),
+        );
+        let backward = close(
+            (0..open.len())
+                .map(|i| open[(start + open.len() - i) % open.len()])
+                .collect(),
+        );
+        if backward < forward {
+            (backward, true)
+        } else {
+            (forward, false)
+        }
+    } else {
+        let backward: Vec<_> = nodes.iter().rev().cloned().collect();
+        if backward.as_slice() < nodes {
+            (backward, true)
+        } else {
+            (nodes.to_vec(), false)
+        }
+    }
+}
+
+#[test]
+fn test_simplify_shared_border() {
+    use geo::Area;
+
+    let mut builder = osm_builder::OsmBuilder::new();
+    // the border between the two boundaries zigzags from (2, 0) to (2, 2)
+    let border = vec![
+        named_node(2.0, 0.0, "bottom"),
+        named_node(2.01, 0.5, "z1"),
+        named_node(1.99, 1.0, "z2"),
+        named_node(2.01, 1.5, "z3"),
+        named_node(2.0, 2.0, "top"),
+    ];
+    let left = builder
+        .relation()
+        .outer(border.clone())
+        .outer(vec![
+            named_node(2.0, 2.0, "top"),
+            named_node(0.0, 2.0, "a"),
+            named_node(0.0, 0.0, "b"),
+            named_node(2.0, 0.0, "bottom"),
+        ])
+        .relation_id;
+    let right = builder
+        .relation()
+        .outer(border)
+        .outer(vec![
+            named_node(2.0, 2.0, "top"),
+            named_node(4.0, 2.0, "c"),
+            named_node(4.0, 0.0, "d"),
+            named_node(2.0, 0.0, "bottom"),
+        ])
+        .inner(vec![
+            named_node(3.0, 0.5, "e"),
+            named_node(3.5, 0.5, "f"),
+            named_node(3.5, 1.0, "g"),
+            named_node(3.0, 0.5, "e"),
+        ])
+        .relation_id;
+    let rings: Vec<_> = [left, right]
+        .iter()
+        .map(|id| {
+            let relation = builder.objects[&(*id).into()].relation().unwrap();
+            let rings = BoundaryBuilder::new()
+                .build_rings(relation, &builder.objects)
+                .boundary
+                .unwrap();
+            (*id, rings)
+        })
+        .collect();
+
+    let topology = Topology::new(rings.iter().map(|(id, rings)| (*id, rings)));
+    assert_eq!(topology.arcs.len(), 4);
+    assert_eq!(
+        topology.multipolygon(left),
+        Some(rings[0].1.to_multipolygon())
+    );
+
+    let simplified = topology.simplify(0.05, Simplification::DouglasPeucker);
+    let border = &simplified.arcs[simplified.boundaries[&left][0][0][0].arc];
+    assert_eq!(border.coords.0.len(), 2);
+    let multipolygons = simplified.multipolygons();
+    assert!((multipolygons[&left].unsigned_area() - 4.).abs() < 1e-7);
+    assert!((multipolygons[&right].unsigned_area() - 3.875).abs() < 1e-7);
+    assert_eq!(multipolygons[&right].0[0].interiors().len(), 1);
+
+    // the inner ring collapses
+    let simplified = topology.simplify(1., Simplification::DouglasPeucker);
+    assert_eq!(
+        simplified.multipolygon(right).unwrap().0[0]
+            .interiors()
+            .len(),
+        0
+    );
+}
+
+#[test]
+fn test_topology_with_crossings_in_several_boundaries() {
+    use crate::boundaries::RingAssembly;
+    use geo::{Area, BoundingRect};
+
+    // two squares crossing each other, shifted by `dx`
+    let mut builder = osm_builder::OsmBuilder::new();
+    let mut crossing_relation = |dx: f64| {
+        let node = |x: f64, y: f64| {
+            let name = format!("{} {}", x + dx, y);
+            (geo_types::Point::new(x + dx, y), Some(name))
+        };
+        builder
+            .relation()
+            .outer(vec![
+                node(0.0, 0.0),
+                node(4.0, 0.0),
+                node(4.0, 4.0),
+                node(0.0, 4.0),
+                node(0.0, 0.0),
+            ])
+            .outer(vec![
+                node(2.0, 2.0),
+                node(6.0, 2.0),
+                node(6.0, 6.0),
+                node(2.0, 6.0),
+                node(2.0, 2.0),
+            ])
+            .relation_id
+    };
+    let first = crossing_relation(0.);
+    let second = crossing_relation(100.);
+    let rings: Vec<_> = [first, second]
+        .iter()
+        .map(|id| {
+            let relation = builder.objects[&(*id).into()].relation().unwrap();
+            let rings = BoundaryBuilder::new()
+                .ring_assembly(RingAssembly::Faces)
+                .build_rings(relation, &builder.objects)
+                .boundary
+                .unwrap();
+            (*id, rings)
+        })
+        .collect();
+    // both boundaries number their crossing vertices from -1
+    assert_eq!(
+        rings[0].1.polygons[0]
+            .exterior
+            .nodes
+            .iter()
+            .filter(|n| n.0 < 0)
+            .count(),
+        2
+    );
+
+    let topology = Topology::new(rings.iter().map(|(id, rings)| (*id, rings)));
+    for (id, rings) in &rings {
+        let multipolygon = topology.multipolygon(*id).unwrap();
+        assert!((multipolygon.unsigned_area() - 28.).abs() < 1e-7);
+        assert_eq!(
+            multipolygon.bounding_rect(),
+            rings.to_multipolygon().bounding_rect()
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use geo::coordinate_position::CoordPos;
+use geo::dimensions::Dimensions;
+use geo::{BooleanOps, BoundingRect, Intersects, Relate};
+use geo_types::{LineString, MultiPolygon, Polygon};
+
+/// Problem making a multipolygon invalid according to the OGC simple
+/// features rules.
+///
+/// `polygon` is the index of the polygon in the multipolygon, and `ring`
+/// is `None` for its exterior or the index of one of its interiors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidityIssue {
+    /// The ring crosses or touches itself.
+    SelfIntersection { polygon: usize, ring: Option<usize> },
+    /// The exterior is not counter-clockwise, or the interior not clockwise.
+    WrongOrientation { polygon: usize, ring: Option<usize> },
+    /// The interior is not inside the exterior of its polygon, or crosses it.
+    HoleOutsideShell { polygon: usize, ring: usize },
+    /// The two interiors of the polygon overlap.
+    OverlappingHoles {
+        polygon: usize,
+        ring: usize,
+        other: usize,
+    },
+    /// The interiors of the two polygons overlap.
+    OverlappingShells { polygon: usize, other: usize },
+}
+
+/// Whether `ring` lies inside `area`, possibly touching its boundary.
+///
+/// Using 'contains' is not suitable here, as a ring may touch its enclosing
+/// ring at a single point, so `ring` is considered inside if all its segments
+/// share a point with (i.e 'intersects') `area`.
+pub(crate) fn encloses(area: &Polygon<f64>, ring: &LineString<f64>) -> bool {
+    ring.lines().all(|line| area.intersects(&line))
+}
+
+/// Whether no part of `polygon` lies outside `area`, which it may touch.
+pub(crate) fn is_within(polygon: &Polygon<f64>, area: &Polygon<f64>) -> bool {
+    polygon
+        .relate(area)
+        .get(CoordPos::Inside, CoordPos::Outside)
+        == Dimensions::Empty
+}
+
+/// Whether two non adjacent segments of a ring share a point.
+///
+/// The segments are indexed in an R-tree, so that only the segments whose
+/// bounding boxes intersect are compared.
+pub(crate) fn is_self_intersecting(ring: &LineString<f64>) -> bool {
+    use geo::line_intersection::line_intersection;
+    use rstar::primitives::{GeomWithData, Line};
+    use rstar::{RTree, AABB};
+
+    let mut ring = ring.clone();
+    ring.close();
+    let lines: Vec<_> = ring.lines().collect();
+    let n = lines.len();
+    let tree: RTree<GeomWithData<Line<[f64; 2]>, usize>> = RTree::bulk_load(
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, l)| GeomWithData::new(Line::new(l.start.into(), l.end.into()), i))
+            .collect(),
+    );
+    lines.iter().enumerate().any(|(i, line)| {
+        let envelope = AABB::from_corners(line.start.into(), line.end.into());
+        tree.locate_in_envelope_intersecting(&envelope)
+            .map(|other| other.data)
+            .filter(|&j| j >= i + 2 && (i != 0 || j != n - 1))
+            .any(|j| line_intersection(*line, lines[j]).is_some())
+    })
+}
+
+/// Checks a multipolygon against the OGC validity rules, returning all the
+/// problems found.
+pub fn validate_multipolygon(multipolygon: &MultiPolygon<f64>) -> Vec<ValidityIssue> {
+    use geo::winding_order::Winding;
+
+    let mut issues = vec![];
+    for (p, polygon) in multipolygon.0.iter().enumerate() {
+        let rings = std::iter::once((None, polygon.exterior())).chain(
+            polygon
+                .interiors()
+                .iter()
+                .enumerate()
+                .map(|(i, r)| (Some(i), r)),
+        );
+        for (ring, line_string) in rings {
+            if is_self_intersecting(line_string) {
+                issues.push(ValidityIssue::SelfIntersection { polygon: p, ring });
+            }
+            let well_oriented = match ring {
+                None => line_string.is_ccw(),
+                Some(_) => line_string.is_cw(),
+            };
+            if !well_oriented {
+                issues.push(ValidityIssue::WrongOrientation { polygon: p, ring });
+            }
+        }
+
+        let shell = Polygon::new(polygon.exterior().clone(), vec![]);
+        let holes: Vec<_> = polygon
+            .interiors()
+            .iter()
+            .map(|interior| Polygon::new(interior.clone(), vec![]))
+            .collect();
+        for (i, hole) in holes.iter().enumerate() {
+            // a hole may touch its shell, but no part of it may be outside
+            if !is_within(hole, &shell) {
+                issues.push(ValidityIssue::HoleOutsideShell {
+                    polygon: p,
+                    ring: i,
+                });
+            }
+        }
+        for (ring, other) in overlapping_pairs(&holes) {
+            issues.push(ValidityIssue::OverlappingHoles {
+                polygon: p,
+                ring,
+                other,
+            });
+        }
+    }
+
+    for (polygon, other) in overlapping_pairs(&multipolygon.0) {
+        issues.push(ValidityIssue::OverlappingShells { polygon, other });
+    }
+    issues
+}
+
+/// Indexes of the pairs of polygons whose interiors overlap.
+fn overlapping_pairs(polygons: &[Polygon<f64>]) -> Vec<(usize, usize)> {
+    let rects: Vec<_> = polygons.iter().map(|p| p.bounding_rect()).collect();
+    let mut pairs = vec![];
+    for (p, polygon) in polygons.iter().enumerate() {
+        for (other, other_polygon) in polygons.iter().enumerate().skip(p + 1) {
+            let may_overlap = match (rects[p], rects[other]) {
+                (Some(rect), Some(other_rect)) => rect.intersects(&other_rect),
+                _ => false,
+            };
+            if may_overlap
+                && polygon
+                    .relate(other_polygon)
+                    .get(CoordPos::Inside, CoordPos::Inside)
+                    != Dimensions::Empty
+            {
+                pairs.push((p, other));
+            }
+        }
+    }
+    pairs
+}
+
+/// Rebuilds a multipolygon as the union of its polygons, which splits the
+/// self-intersecting rings (e.g. bow-ties) at their intersections, merges
+/// the overlapping polygons, and orients the rings.
+pub fn make_valid(multipolygon: &MultiPolygon<f64>) -> MultiPolygon<f64> {
+    multipolygon
+        .0
+        .iter()
+        .fold(MultiPolygon(vec![]), |valid, polygon| {
+            valid.union(&MultiPolygon(vec![polygon.clone()]))
+        })
+}
+
+#[test]
+fn test_validate_multipolygon() {
+    use geo_types::polygon;
+
+    let bow_tie = polygon![
+        (x: 0., y: 0.), (x: 2., y: 2.), (x: 2., y: 0.), (x: 0., y: 2.), (x: 0., y: 0.)
+    ];
+    let square = polygon!(
+        exterior: [(x: 10., y: 10.), (x: 14., y: 10.), (x: 14., y: 14.), (x: 10., y: 14.)],
+        interiors: [[(x: 15., y: 15.), (x: 16., y: 15.), (x: 16., y: 16.)]],
+    );
+    let overlapping = polygon![
+        (x: 12., y: 12.), (x: 16., y: 12.), (x: 16., y: 16.), (x: 12., y: 16.)
+    ];
+    let multipolygon = MultiPolygon(vec![bow_tie, square, overlapping]);
+    assert_eq!(
+        validate_multipolygon(&multipolygon),
+        vec![
+            ValidityIssue::SelfIntersection {
+                polygon: 0,
+                ring: None
+            },
+            ValidityIssue::WrongOrientation {
+                polygon: 1,
+                ring: Some(0)
+            },
+            ValidityIssue::HoleOutsideShell {
+                polygon: 1,
+                ring: 0
+            },
+            ValidityIssue::OverlappingShells {
+                polygon: 1,
+                other: 2
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_validate_multipolygon_with_crossing_holes() {
+    use geo_types::polygon;
+
+    let crossing = polygon!(
+        exterior: [(x: 0., y: 0.), (x: 4., y: 0.), (x: 4., y: 4.), (x: 0., y: 4.)],
+        interiors: [[(x: 1., y: 1.), (x: 1., y: 3.), (x: 6., y: 1.)]],
+    );
+    assert_eq!(
+        validate_multipolygon(&MultiPolygon(vec![crossing])),
+        vec![ValidityIssue::HoleOutsideShell {
+            polygon: 0,
+            ring: 0
+        }]
+    );
+
+    // holes touching their shell or each other at a point are valid
+    let touching = polygon!(
+        exterior: [(x: 0., y: 0.), (x: 4., y: 0.), (x: 4., y: 4.), (x: 0., y: 4.)],
+        interiors: [
+            [(x: 0., y: 2.), (x: 2., y: 3.), (x: 2., y: 1.)],
+            [(x: 2., y: 1.), (x: 3., y: 2.), (x: 3., y: 1.)],
+        ],
+    );
+    assert!(validate_multipolygon(&MultiPolygon(vec![touching])).is_empty());
+}
+
+#[test]
+fn test_validate_multipolygon_with_overlapping_holes() {
+    use geo_types::polygon;
+
+    let overlapping = polygon!(
+        exterior: [(x: 0., y: 0.), (x: 10., y: 0.), (x: 10., y: 10.), (x: 0., y: 10.)],
+        interiors: [
+            [(x: 1., y: 1.), (x: 1., y: 5.), (x: 5., y: 5.), (x: 5., y: 1.)],
+            [(x: 8., y: 8.), (x: 8., y: 9.), (x: 9., y: 9.), (x: 9., y: 8.)],
+            [(x: 3., y: 3.), (x: 3., y: 7.), (x: 7., y: 7.), (x: 7., y: 3.)],
+        ],
+    );
+    assert_eq!(
+        validate_multipolygon(&MultiPolygon(vec![overlapping])),
+        vec![ValidityIssue::OverlappingHoles {
+            polygon: 0,
+            ring: 0,
+            other: 2
+        }]
+    );
+}
+
+#[test]
+fn test_make_valid() {
+    use geo::Area;
+    use geo_types::polygon;
+
+    let bow_tie = polygon![
+        (x: 0., y: 0.), (x: 2., y: 2.), (x: 2., y: 0.), (x: 0., y: 2.), (x: 0., y: 0.)
+    ];
+    let square = polygon![(x: 10., y: 10.), (x: 14., y: 10.), (x: 14., y: 14.), (x: 10., y: 14.)];
+    let overlapping = polygon![
+        (x: 12., y: 12.), (x: 16., y: 12.), (x: 16., y: 16.), (x: 12., y: 16.)
+    ];
+    let valid = make_valid(&MultiPolygon(vec![bow_tie, square, overlapping]));
+    assert!(validate_multipolygon(&valid).is_empty());
+    assert_eq!(valid.0.len(), 3);
+    assert!((valid.unsigned_area() - 30.).abs() < 1e-7);
+}